@@ -13,15 +13,22 @@ pub enum StringPart {
 pub enum Expr {
     Int(i64),
     Float(f64),
+    /// Duration literal in milliseconds: `250ms`, `5m`, `2h`, `7d`
+    Duration(i64),
     Bool(bool),
     Str(String),
     Null,
     List(Vec<Expr>),
-    Map(Vec<(String, Expr)>),
+    Map(Vec<MapEntry>),
+    /// Set literal: `{1, 2, 3}` (the empty set is `set()`)
+    Set(Vec<Expr>),
 
     Ident(String),
 
     Interpolated(Vec<StringPart>),
+    /// `sh"cp ${src} ${dst}"` — a shell command whose interpolated values are
+    /// quoted as single words (lists as one word per item)
+    ShellCommand(Vec<StringPart>),
 
     BinOp {
         op: BinOp,
@@ -37,15 +44,28 @@ pub enum Expr {
     Call {
         name: String,
         args: Vec<Expr>,
-        kwargs: Vec<(String, Expr)>,  // Keyword arguments
+        kwargs: KwArgs,
     },
 
     ModuleCall {
         module: String,
         method: String,
         args: Vec<Expr>,
+        kwargs: KwArgs,
     },
 
+    /// `expr.method(args)` on anything other than a plain name, e.g.
+    /// `self.conn.close()` or `make().run()`
+    MethodCall {
+        object: Box<Expr>,
+        method: String,
+        args: Vec<Expr>,
+        kwargs: KwArgs,
+    },
+
+    /// Spread: `...xs` in a call's arguments or a list literal
+    Spread(Box<Expr>),
+
     Index {
         expr: Box<Expr>,
         index: Box<Expr>,
@@ -57,6 +77,20 @@ pub enum Expr {
         default: Box<Expr>,
     },
 
+    /// `match subject { pattern => expr, pattern if guard => { ... } }` —
+    /// the first matching arm's value (block arms give `null`)
+    Match {
+        subject: Box<Expr>,
+        arms: Vec<MatchArm>,
+    },
+
+    /// `try? expr` — evaluate to `ok(value)`, or `err(message)` if it raised
+    TryResult(Box<Expr>),
+
+    /// `expr?` — unwrap an `ok` result; return an `err` (or a raised error,
+    /// as an `err`) from the enclosing function
+    Propagate(Box<Expr>),
+
     /// Field access like `result.stdout`
     FieldAccess {
         expr: Box<Expr>,
@@ -90,9 +124,20 @@ pub enum Expr {
     /// List comprehension: `[x*2 for x in list]` or `[x for x in list if x > 0]`
     ListComp {
         body: Box<Expr>,           // The expression to generate (e.g., x*2)
-        var: String,               // Loop variable (e.g., x)
-        iter: Box<Expr>,           // Iterable (e.g., list)
-        cond: Option<Box<Expr>>,   // Optional condition (e.g., x > 0)
+        clauses: Vec<CompClause>,  // `for`/`if` clauses, outermost first
+    },
+
+    /// Set comprehension: `{x % 3 for x in list}`
+    SetComp {
+        body: Box<Expr>,
+        clauses: Vec<CompClause>,
+    },
+
+    /// Dict comprehension: `{k: v * 2 for k, v in items(d) if v > 0}`
+    DictComp {
+        key: Box<Expr>,
+        value: Box<Expr>,
+        clauses: Vec<CompClause>,
     },
 
     /// Safe field access: `expr?.field`
@@ -113,7 +158,12 @@ pub enum Expr {
         expr: Box<Expr>,
         start: Option<Box<Expr>>,
         end: Option<Box<Expr>>,
+        step: Option<Box<Expr>>,
     },
+
+    /// Background task: `spawn fn() { ... }` or `spawn { ... }` (sugar for a
+    /// zero-argument fn). Evaluates to a task handle.
+    Spawn(Box<Expr>),
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -122,6 +172,8 @@ pub enum BinOp {
     Eq, NotEq, Lt, Gt, LtEq, GtEq,
     And, Or,
     In,
+    /// `|` `&` `^` — bitwise on ints; union / intersection / symmetric difference on sets
+    BitOr, BitAnd, BitXor,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -157,16 +209,22 @@ pub enum Stmt {
         else_: Option<Box<Stmt>>, // Box<Stmt::If> for elif, Box<Stmt::Block> for else
     },
 
+    /// `label: for var in iter { body } else { else_body }` — the label and
+    /// the `else` block (run when the loop ends without `break`) are optional
     For {
+        label: Option<String>,
         var: String,
         iter: Expr,
         body: Block,
+        else_body: Option<Block>,
     },
 
     Parallel {
         var: String,
         iter: Expr,
         workers: Option<Expr>,
+        /// `output="ordered"|"prefix"|"interleaved"`
+        output: Option<Expr>,
         body: Block,
     },
 
@@ -198,21 +256,46 @@ pub enum Stmt {
         value: Expr,
     },
 
-    /// `while condition { body }`
+    /// `label: while condition { body } else { else_body }`
     While {
+        label: Option<String>,
         cond: Expr,
         body: Block,
+        else_body: Option<Block>,
     },
 
-    /// `break` — exit the innermost loop
-    Break,
+    /// `break` / `break label` — exit the innermost (or the labeled) loop
+    Break(Option<String>),
 
-    /// `continue` — skip to the next iteration of the innermost loop
-    Continue,
+    /// `continue` / `continue label` — skip to the next iteration of the
+    /// innermost (or the labeled) loop
+    Continue(Option<String>),
 
     /// `stop 1` — exit the script with a code
     Stop(Expr),
 
+    /// `set strict` / `set strict = false` — change a script-wide option
+    SetOption { name: String, value: Expr },
+
+    /// `defer expr` / `defer { ... }` — run when the enclosing scope exits
+    /// (normally, on error, or on `stop`), most recent first
+    Defer(Block),
+
+    /// `with fs.tempdir() as d { ... }` — scoped resource, released when
+    /// the block exits
+    With {
+        resource: Expr,
+        name: Option<String>,
+        body: Block,
+    },
+
+    /// Field assignment: `self.port = 80`, `cfg.retries = 3`
+    FieldAssign {
+        target: Expr,
+        field: String,
+        value: Expr,
+    },
+
     /// Compound assignment: `x += 1`, `x -= 2`, etc.
     CompoundAssign {
         name: String,
@@ -223,13 +306,27 @@ pub enum Stmt {
     /// A bare expression used as a statement: `print("hi")`
     Expr(Expr),
 
-    /// Class declaration: `class Point { x: int, y: int, fn move() { ... } }`
+    /// `enum Env { Dev, Staging, Prod }`, `enum Outcome { Ok(value), Failed(code, msg) }`
+    Enum {
+        name: String,
+        variants: Vec<EnumVariant>,
+    },
+
+    /// Class declaration: `class Service extends Base implements Named { port: int, fn init() { ... } }`
     Class {
         name: String,
+        parent: Option<String>,
+        interfaces: Vec<String>,
         fields: Vec<(String, Option<Type>, Option<Expr>)>, // name, type, default
         methods: Vec<(String, Vec<Param>, Block)>, // name, params, body
     },
 
+    /// `interface Named { fn name() }` — methods a class must provide
+    Interface {
+        name: String,
+        methods: Vec<(String, Vec<Param>)>,
+    },
+
     /// Export statement: `export { foo, bar }` or `export foo`
     Export(Vec<String>),
 
@@ -242,11 +339,81 @@ pub enum Stmt {
 
 pub type Block = Vec<Stmt>;
 
+/// Keyword arguments of a call: `name=expr`, in source order.
+pub type KwArgs = Vec<(String, Expr)>;
+
+/// One variant of an `enum`, with the names of its payload fields.
+#[derive(Debug, Clone)]
+pub struct EnumVariant {
+    pub name: String,
+    pub fields: Vec<String>,
+}
+
+/// `pattern | pattern if guard => body`
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub patterns: Vec<Pattern>,
+    pub guard: Option<Expr>,
+    pub body: MatchBody,
+}
+
+#[derive(Debug, Clone)]
+pub enum MatchBody {
+    Expr(Expr),
+    Block(Block),
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    /// `_` — matches anything
+    Wildcard,
+    /// `name` — matches anything and binds it
+    Binding(String),
+    /// `"prod"`, `404`, `-1`, `true`, `null` — matches an equal value
+    Literal(Expr),
+    /// `Env.Dev`, `Outcome.Failed(code, _)`. Without parentheses a payload
+    /// variant matches whatever its payload is.
+    Variant {
+        enum_name: String,
+        variant: String,
+        bindings: Option<Vec<String>>,
+    },
+}
+
 #[derive(Debug, Clone)]
 pub struct Param {
     pub name: String,
     pub type_ann: Option<Type>,
     pub default: Option<Expr>,  // Default value for optional parameter
+    pub kind: ParamKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParamKind {
+    Normal,
+    /// `...rest` — extra positional arguments, as a list
+    Rest,
+    /// `**opts` — unmatched keyword arguments, as a dict
+    KwRest,
+}
+
+/// One entry of a dict literal.
+#[derive(Debug, Clone)]
+pub enum MapEntry {
+    /// `key: value`; a bare-name key is parsed as a string literal
+    Pair(Expr, Expr),
+    /// `...other` — copy every entry of another dict
+    Spread(Expr),
+}
+
+/// One clause of a comprehension. Clauses nest left to right, like loops:
+/// `[[x, y] for x in a if x > 0 for y in b]`.
+#[derive(Debug, Clone)]
+pub enum CompClause {
+    /// `for x in iter` or `for k, v in pairs` (each item unpacked into the names)
+    For { vars: Vec<String>, iter: Expr },
+    /// `if cond`
+    If(Expr),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -257,6 +424,10 @@ pub enum Type {
     Str,
     List,
     Dict,
+    Set,
+    Bytes,
+    DateTime,
+    Duration,
     Process,
     File,
     Any,
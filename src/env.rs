@@ -1,10 +1,18 @@
 use std::collections::HashMap;
 use std::fmt;
-use std::sync::{Arc, Mutex};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 
-use crate::ast::{Block, Param};
+use indexmap::{IndexMap, IndexSet};
+
+use crate::ast::{Block, Expr, Param};
 use crate::error::{LatchError, Result};
 
+/// Dict storage: any hashable key, kept in insertion order.
+pub type Dict = IndexMap<HashKey, Value>;
+
 /// Runtime value – the result of evaluating any expression.
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -13,49 +21,91 @@ pub enum Value {
     Float(f64),
     Bool(bool),
     Str(String),
+    Bytes(Arc<Vec<u8>>),
     List(Arc<Mutex<Vec<Value>>>),
-    Map(Arc<Mutex<HashMap<String, Value>>>),
+    Map(Arc<Mutex<Dict>>),
+    Set(Arc<Mutex<IndexSet<HashKey>>>),
+    DateTime(chrono::DateTime<chrono::FixedOffset>),
+    Duration(chrono::TimeDelta),
     Fn {
         params: Vec<Param>,
         body: Block,
         captured_env: Option<Box<Env>>,
     },
-    ProcessResult {
-        stdout: String,
-        stderr: String,
-        code: i32,
-    },
+    ProcessResult(ProcessOutput),
     HttpResponse {
         status: i64,
-        body: String,
+        body: Vec<u8>,
         headers: HashMap<String, String>,
     },
+    Task(Arc<TaskState>),
+    /// A background process started by `proc.spawn`
+    Process(Arc<crate::runtime::proc::ProcHandle>),
+    Resource(Arc<Resource>),
+    /// `ok(value)` / `err(error)`, also produced by `try? expr`
+    Result(std::result::Result<Box<Value>, Box<Value>>),
+    /// An `enum` declaration, bound to its name: `Env`
+    EnumType(Arc<EnumDef>),
+    /// One of its variants: `Env.Dev`, `Outcome.Failed(2, "boom")`
+    Enum(Arc<EnumValue>),
+    /// A `class` declaration, bound to its name; calling it builds an instance
+    Class(Arc<ClassDef>),
+    /// An `interface` declaration, bound to its name
+    Interface(Arc<InterfaceDef>),
+    /// An object built by calling a class
+    Instance(Arc<Instance>),
     Null,
 }
 
 impl Value {
+    /// `err(message)` for a raised error, as produced by `try?` and `?`.
+    pub fn err_from(e: &LatchError) -> Value {
+        Value::Result(Err(Box::new(Value::Str(e.reason()))))
+    }
+
     pub fn type_name(&self) -> &'static str {
         match self {
             Value::Int(_)            => "int",
             Value::Float(_)          => "float",
             Value::Bool(_)           => "bool",
             Value::Str(_)            => "string",
+            Value::Bytes(_)          => "bytes",
             Value::List(_)           => "list",
             Value::Map(_)            => "dict",
+            Value::Set(_)            => "set",
+            Value::DateTime(_)       => "datetime",
+            Value::Duration(_)       => "duration",
             Value::Fn { .. }         => "fn",
-            Value::ProcessResult { .. } => "process",
+            Value::ProcessResult(_)  => "process",
             Value::HttpResponse { .. }  => "response",
+            Value::Task(_)           => "task",
+            Value::Process(_)        => "proc",
+            Value::Resource(_)       => "resource",
+            Value::Result(_)         => "result",
+            Value::EnumType(_) | Value::Enum(_) => "enum",
+            Value::Class(_)          => "class",
+            Value::Interface(_)      => "interface",
+            Value::Instance(_)       => "object",
             Value::Null              => "null",
         }
     }
 
+    pub fn new_bytes(data: Vec<u8>) -> Value {
+        Value::Bytes(Arc::new(data))
+    }
+
+    /// Construct a new reference-counted set, keeping first-insertion order.
+    pub fn new_set(items: impl IntoIterator<Item = HashKey>) -> Value {
+        Value::Set(Arc::new(Mutex::new(items.into_iter().collect())))
+    }
+
     /// Construct a new reference-counted list.
     pub fn new_list(items: Vec<Value>) -> Value {
         Value::List(Arc::new(Mutex::new(items)))
     }
 
     /// Construct a new reference-counted dict.
-    pub fn new_map(map: HashMap<String, Value>) -> Value {
+    pub fn new_map(map: Dict) -> Value {
         Value::Map(Arc::new(Mutex::new(map)))
     }
 
@@ -95,6 +145,13 @@ impl Value {
     pub fn as_str(&self) -> Result<&str> {
         match self {
             Value::Str(s) => Ok(s),
+            Value::Resource(r) => match &r.kind {
+                ResourceKind::TempDir(p) => Ok(p.to_str().unwrap_or_default()),
+                _ => Err(LatchError::TypeMismatch {
+                    expected: "string".into(),
+                    found: self.type_name().into(),
+                }),
+            },
             _ => Err(LatchError::TypeMismatch {
                 expected: "string".into(),
                 found: self.type_name().into(),
@@ -102,9 +159,24 @@ impl Value {
         }
     }
 
+    pub fn as_bytes(&self) -> Result<&[u8]> {
+        match self {
+            Value::Bytes(b) => Ok(b),
+            Value::Str(s) => Ok(s.as_bytes()),
+            _ => Err(LatchError::TypeMismatch {
+                expected: "bytes".into(),
+                found: self.type_name().into(),
+            }),
+        }
+    }
+
     pub fn as_list(&self) -> Result<Vec<Value>> {
         match self {
             Value::List(l) => Ok(l.lock().unwrap().clone()),
+            Value::Bytes(b) => Ok(b.iter().map(|&x| Value::Int(x as i64)).collect()),
+            Value::Set(s) => Ok(s.lock().unwrap().iter().map(HashKey::to_value).collect()),
+            // Iterating a dict yields its keys, in insertion order
+            Value::Map(m) => Ok(m.lock().unwrap().keys().map(HashKey::to_value).collect()),
             _ => Err(LatchError::TypeMismatch {
                 expected: "list".into(),
                 found: self.type_name().into(),
@@ -115,6 +187,9 @@ impl Value {
     pub fn into_list(self) -> Result<Vec<Value>> {
         match self {
             Value::List(l) => Ok(l.lock().unwrap().clone()),
+            Value::Bytes(b) => Ok(b.iter().map(|&x| Value::Int(x as i64)).collect()),
+            Value::Set(s) => Ok(s.lock().unwrap().iter().map(HashKey::to_value).collect()),
+            Value::Map(m) => Ok(m.lock().unwrap().keys().map(HashKey::to_value).collect()),
             _ => Err(LatchError::TypeMismatch {
                 expected: "list".into(),
                 found: self.type_name().into(),
@@ -128,6 +203,8 @@ impl Value {
             Value::Bool(false) | Value::Null => false,
             Value::Int(0) => false,
             Value::Str(s) if s.is_empty() => false,
+            Value::Duration(d) if d.is_zero() => false,
+            Value::Result(r) => r.is_ok(),
             _ => true,
         }
     }
@@ -140,6 +217,22 @@ impl fmt::Display for Value {
             Value::Float(n) => write!(f, "{n}"),
             Value::Bool(b) => write!(f, "{b}"),
             Value::Str(s) => write!(f, "{s}"),
+            Value::Bytes(b) => {
+                // b"..." with printable ASCII kept and everything else as \xNN
+                write!(f, "b\"")?;
+                for &byte in b.iter() {
+                    match byte {
+                        b'"' => write!(f, "\\\"")?,
+                        b'\\' => write!(f, "\\\\")?,
+                        b'\n' => write!(f, "\\n")?,
+                        b'\r' => write!(f, "\\r")?,
+                        b'\t' => write!(f, "\\t")?,
+                        0x20..=0x7e => write!(f, "{}", byte as char)?,
+                        _ => write!(f, "\\x{byte:02x}")?,
+                    }
+                }
+                write!(f, "\"")
+            }
             Value::Null => write!(f, "null"),
             Value::List(items) => {
                 let items = items.lock().unwrap();
@@ -152,23 +245,469 @@ impl fmt::Display for Value {
             }
             Value::Map(map) => {
                 let map = map.lock().unwrap();
-                let mut sorted_entries: Vec<_> = map.iter().collect();
-                sorted_entries.sort_by_key(|(k, _)| (*k).clone());
                 write!(f, "{{")?;
-                for (i, (k, v)) in sorted_entries.iter().enumerate() {
+                for (i, (k, v)) in map.iter().enumerate() {
                     if i > 0 { write!(f, ", ")?; }
                     write!(f, "{k}: {v}")?;
                 }
                 write!(f, "}}")
             }
+            Value::Set(set) => {
+                let set = set.lock().unwrap();
+                if set.is_empty() {
+                    return write!(f, "set()");
+                }
+                write!(f, "{{")?;
+                for (i, k) in set.iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{}", k.to_value())?;
+                }
+                write!(f, "}}")
+            }
+            Value::DateTime(dt) => write!(f, "{}", dt.to_rfc3339()),
+            Value::Duration(d) => write!(f, "{}", crate::runtime::time::format_duration(*d)),
             Value::Fn { .. } => write!(f, "<fn>"),
-            Value::ProcessResult { stdout, stderr, code } => {
-                write!(f, "ProcessResult(code={code}, stdout={stdout:?}, stderr={stderr:?})")
+            Value::ProcessResult(p) => {
+                write!(f, "ProcessResult(code={}, stdout={:?}, stderr={:?})", p.code, p.stdout_text(), p.stderr_text())
             }
             Value::HttpResponse { status, body, .. } => {
-                let preview = if body.len() > 80 { &body[..80] } else { body.as_str() };
+                let preview = String::from_utf8_lossy(&body[..body.len().min(80)]);
                 write!(f, "HttpResponse(status={status}, body={preview:?}...)")
             }
+            Value::Task(task) => {
+                if task.is_done() { write!(f, "<task done>") } else { write!(f, "<task running>") }
+            }
+            Value::Process(h) => write!(f, "{h}"),
+            Value::Resource(r) => match &r.kind {
+                ResourceKind::TempDir(p) => write!(f, "{}", p.display()),
+                ResourceKind::EnvVars(_) => write!(f, "<env scope>"),
+            },
+            Value::Result(Ok(v)) => write!(f, "ok({v})"),
+            Value::Result(Err(e)) => write!(f, "err({e})"),
+            Value::Class(def) => write!(f, "<class {}>", def.name),
+            Value::Interface(def) => write!(f, "<interface {}>", def.name),
+            Value::Instance(obj) => {
+                write!(f, "{}(", obj.class.name)?;
+                for (i, (k, v)) in obj.fields.lock().unwrap().iter().enumerate() {
+                    if i > 0 { write!(f, ", ")?; }
+                    write!(f, "{k}={v}")?;
+                }
+                write!(f, ")")
+            }
+            Value::EnumType(def) => write!(f, "<enum {}>", def.name),
+            Value::Enum(e) => {
+                write!(f, "{}.{}", e.def.name, e.variant_name())?;
+                if !e.payload.is_empty() {
+                    write!(f, "(")?;
+                    for (i, v) in e.payload.iter().enumerate() {
+                        if i > 0 { write!(f, ", ")?; }
+                        write!(f, "{v}")?;
+                    }
+                    write!(f, ")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+// ── Enums ────────────────────────────────────────────────────
+
+/// A user-defined `enum`: its name and each variant's payload field names.
+#[derive(Debug, PartialEq, Eq, Hash)]
+pub struct EnumDef {
+    pub name: String,
+    pub variants: Vec<(String, Vec<String>)>,
+}
+
+impl EnumDef {
+    pub fn variant_index(&self, name: &str) -> Option<usize> {
+        self.variants.iter().position(|(v, _)| v == name)
+    }
+
+    /// Build `Enum.variant(payload...)`, checking the payload size.
+    pub fn construct(self: &Arc<Self>, variant: &str, payload: Vec<Value>) -> Result<Value> {
+        let index = self.variant_index(variant).ok_or_else(|| LatchError::KeyNotFound(format!("{}.{variant}", self.name)))?;
+        let expected = self.variants[index].1.len();
+        if payload.len() != expected {
+            return Err(LatchError::ArgCountMismatch {
+                name: format!("{}.{variant}", self.name),
+                expected,
+                found: payload.len(),
+            });
+        }
+        Ok(Value::Enum(Arc::new(EnumValue { def: self.clone(), variant: index, payload })))
+    }
+}
+
+/// A value of an enum: which variant, plus its payload in field order.
+#[derive(Debug)]
+pub struct EnumValue {
+    pub def: Arc<EnumDef>,
+    pub variant: usize,
+    pub payload: Vec<Value>,
+}
+
+impl EnumValue {
+    pub fn variant_name(&self) -> &str {
+        &self.def.variants[self.variant].0
+    }
+
+    /// Payload fields by name; `.variant` gives the variant name unless a
+    /// field shadows it.
+    pub fn field(&self, name: &str) -> Option<Value> {
+        let fields = &self.def.variants[self.variant].1;
+        match fields.iter().position(|f| f == name) {
+            Some(i) => Some(self.payload[i].clone()),
+            None if name == "variant" => Some(Value::Str(self.variant_name().to_string())),
+            None => None,
+        }
+    }
+}
+
+// ── Classes ──────────────────────────────────────────────────
+
+/// A user-defined `class`: its parent, the interfaces it claims, field
+/// defaults (evaluated per instance) and its own methods.
+#[derive(Debug)]
+pub struct ClassDef {
+    pub name: String,
+    pub parent: Option<Arc<ClassDef>>,
+    pub interfaces: Vec<Arc<InterfaceDef>>,
+    pub fields: Vec<(String, Option<Expr>)>,
+    pub methods: HashMap<String, Method>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Method {
+    pub params: Vec<Param>,
+    pub body: Block,
+}
+
+impl ClassDef {
+    /// Look a method up along the parent chain, returning the class that
+    /// defines it (which `super` inside the method starts from).
+    pub fn find_method(self: &Arc<Self>, name: &str) -> Option<(Arc<ClassDef>, Method)> {
+        let mut class = Some(self);
+        while let Some(c) = class {
+            if let Some(m) = c.methods.get(name) {
+                return Some((c.clone(), m.clone()));
+            }
+            class = c.parent.as_ref();
+        }
+        None
+    }
+
+    /// This class followed by its ancestors, nearest first.
+    pub fn ancestry(&self) -> impl Iterator<Item = &ClassDef> {
+        std::iter::successors(Some(self), |c| c.parent.as_deref())
+    }
+
+    pub fn is_subclass_of(&self, other: &ClassDef) -> bool {
+        self.ancestry().any(|c| std::ptr::eq(c, other))
+    }
+
+    pub fn implements(&self, iface: &InterfaceDef) -> bool {
+        self.ancestry().any(|c| c.interfaces.iter().any(|i| std::ptr::eq(&**i, iface)))
+    }
+}
+
+/// An `interface`: method names with their parameters.
+#[derive(Debug)]
+pub struct InterfaceDef {
+    pub name: String,
+    pub methods: Vec<(String, Vec<Param>)>,
+}
+
+/// An object: its class and its fields, shared between all references.
+#[derive(Debug)]
+pub struct Instance {
+    pub class: Arc<ClassDef>,
+    pub fields: Mutex<IndexMap<String, Value>>,
+}
+
+impl Instance {
+    pub fn field(&self, name: &str) -> Option<Value> {
+        self.fields.lock().unwrap().get(name).cloned()
+    }
+}
+
+// ── Process results ──────────────────────────────────────────
+
+/// Captured result of a finished process. Output is kept as raw bytes;
+/// `.stdout` / `.stderr` decode it (lossily) as UTF-8, while
+/// `.stdout_bytes` / `.stderr_bytes` give the exact data.
+#[derive(Debug, Clone, Default)]
+pub struct ProcessOutput {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub code: i32,
+    /// Stopped by its `timeout` option
+    pub timed_out: bool,
+    /// Exit code of each pipeline stage, like bash's `PIPESTATUS`
+    pub pipestatus: Vec<i32>,
+    /// Each stage's stderr, for pipelines of more than one stage
+    pub stage_stderr: Vec<Vec<u8>>,
+    /// Time and memory the command used (boxed to keep `Value` small)
+    pub usage: Box<ResourceUsage>,
+}
+
+/// Resources a finished command used. CPU times and peak memory cover every
+/// stage of a pipeline (the peak is the largest stage's), but not any
+/// background processes it left behind.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ResourceUsage {
+    pub wall: Duration,
+    pub user: Duration,
+    pub sys: Duration,
+    /// Peak resident set size, in bytes
+    pub max_rss: u64,
+}
+
+impl ResourceUsage {
+    /// `{wall, user, sys, max_rss}`, times as durations.
+    pub fn to_value(self) -> Value {
+        let duration = |d: Duration| Value::Duration(chrono::TimeDelta::from_std(d).unwrap_or_default());
+        let mut map = Dict::new();
+        map.insert(HashKey::from("wall"), duration(self.wall));
+        map.insert(HashKey::from("user"), duration(self.user));
+        map.insert(HashKey::from("sys"), duration(self.sys));
+        map.insert(HashKey::from("max_rss"), Value::Int(self.max_rss as i64));
+        Value::new_map(map)
+    }
+}
+
+impl ProcessOutput {
+    pub fn stdout_text(&self) -> String {
+        String::from_utf8_lossy(&self.stdout).into_owned()
+    }
+
+    pub fn stderr_text(&self) -> String {
+        String::from_utf8_lossy(&self.stderr).into_owned()
+    }
+
+    /// Each stage's stderr; a single command has just the one.
+    pub fn stage_stderr_text(&self) -> Vec<String> {
+        if self.stage_stderr.is_empty() {
+            return vec![self.stderr_text()];
+        }
+        self.stage_stderr.iter().map(|e| String::from_utf8_lossy(e).into_owned()).collect()
+    }
+
+    /// Field access: `result.stdout`, `result.code`, ...
+    pub fn field(&self, name: &str) -> Option<Value> {
+        match name {
+            "stdout"       => Some(Value::Str(self.stdout_text())),
+            "stderr"       => Some(Value::Str(self.stderr_text())),
+            "stdout_bytes" => Some(Value::new_bytes(self.stdout.clone())),
+            "stderr_bytes" => Some(Value::new_bytes(self.stderr.clone())),
+            "code"         => Some(Value::Int(self.code as i64)),
+            "timed_out"    => Some(Value::Bool(self.timed_out)),
+            "pipestatus"   => Some(Value::new_list(
+                self.pipestatus.iter().map(|&c| Value::Int(c as i64)).collect(),
+            )),
+            "stage_stderr" => Some(Value::new_list(
+                self.stage_stderr_text().into_iter().map(Value::Str).collect(),
+            )),
+            "usage"        => Some(self.usage.to_value()),
+            _ => None,
+        }
+    }
+}
+
+// ── Hashable values ──────────────────────────────────────────
+
+/// A value frozen into hashable form, used as a set member or dict key.
+/// Lists become tuples (compared element-wise); floats, dicts and functions
+/// are unhashable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashKey {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Bytes(Vec<u8>),
+    Tuple(Vec<HashKey>),
+    Enum(Arc<EnumDef>, usize, Vec<HashKey>),
+}
+
+impl HashKey {
+    pub fn from_value(val: &Value) -> Result<HashKey> {
+        match val {
+            Value::Null => Ok(HashKey::Null),
+            Value::Bool(b) => Ok(HashKey::Bool(*b)),
+            Value::Int(n) => Ok(HashKey::Int(*n)),
+            Value::Str(s) => Ok(HashKey::Str(s.clone())),
+            Value::Bytes(b) => Ok(HashKey::Bytes(b.to_vec())),
+            Value::List(items) => {
+                let items = items.lock().unwrap();
+                Ok(HashKey::Tuple(items.iter().map(HashKey::from_value).collect::<Result<_>>()?))
+            }
+            Value::Enum(e) => {
+                let payload = e.payload.iter().map(HashKey::from_value).collect::<Result<_>>()?;
+                Ok(HashKey::Enum(e.def.clone(), e.variant, payload))
+            }
+            other => Err(LatchError::TypeError(format!("unhashable type: {}", other.type_name()))),
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        match self {
+            HashKey::Null => Value::Null,
+            HashKey::Bool(b) => Value::Bool(*b),
+            HashKey::Int(n) => Value::Int(*n),
+            HashKey::Str(s) => Value::Str(s.clone()),
+            HashKey::Bytes(b) => Value::new_bytes(b.clone()),
+            HashKey::Tuple(items) => Value::new_list(items.iter().map(HashKey::to_value).collect()),
+            HashKey::Enum(def, variant, payload) => Value::Enum(Arc::new(EnumValue {
+                def: def.clone(),
+                variant: *variant,
+                payload: payload.iter().map(HashKey::to_value).collect(),
+            })),
+        }
+    }
+}
+
+impl fmt::Display for HashKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_value())
+    }
+}
+
+impl From<&str> for HashKey {
+    fn from(s: &str) -> Self {
+        HashKey::Str(s.to_string())
+    }
+}
+
+impl From<String> for HashKey {
+    fn from(s: String) -> Self {
+        HashKey::Str(s)
+    }
+}
+
+// ── Background tasks ─────────────────────────────────────────
+
+/// Shared state of a task started with `spawn`.
+///
+/// The worker thread stores its result once; any number of `await` calls
+/// may block on it. Cancellation is cooperative: the worker's interpreter
+/// polls `cancelled` between statements.
+#[derive(Debug, Default)]
+pub struct TaskState {
+    result: Mutex<Option<Result<Value>>>,
+    done: Condvar,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl TaskState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flag handed to the worker interpreter so it can observe `cancel()`.
+    pub fn cancel_flag(&self) -> Arc<AtomicBool> {
+        self.cancelled.clone()
+    }
+
+    /// Record the task's result and wake every waiter.
+    pub fn finish(&self, result: Result<Value>) {
+        *self.result.lock().unwrap() = Some(result);
+        self.done.notify_all();
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.result.lock().unwrap().is_some()
+    }
+
+    /// Request cancellation. Returns false if the task had already finished.
+    pub fn cancel(&self) -> bool {
+        self.cancelled.store(true, Ordering::SeqCst);
+        !self.is_done()
+    }
+
+    /// Block until the task finishes, or until `timeout` elapses (→ None).
+    pub fn wait(&self, timeout: Option<Duration>) -> Option<Result<Value>> {
+        let guard = self.result.lock().unwrap();
+        let guard = match timeout {
+            Some(t) => self.done.wait_timeout_while(guard, t, |r| r.is_none()).unwrap().0,
+            None => self.done.wait_while(guard, |r| r.is_none()).unwrap(),
+        };
+        guard.clone()
+    }
+}
+
+// ── Scoped resources ─────────────────────────────────────────
+
+#[derive(Debug)]
+pub enum ResourceKind {
+    /// Directory created by `fs.tempdir()`; removed recursively on release.
+    TempDir(PathBuf),
+    /// Variables overridden by `env.scoped()`, with their previous values
+    /// (`None` = was unset); restored on release.
+    EnvVars(Vec<(String, Option<String>)>),
+}
+
+/// Something held for the duration of a `with` block, and released when the
+/// block exits. One never used in `with` is left as it is: a temp dir stays
+/// on disk, variables stay set.
+#[derive(Debug)]
+pub struct Resource {
+    kind: ResourceKind,
+    /// Set once a `with` block owns it
+    entered: AtomicBool,
+    released: AtomicBool,
+}
+
+impl Resource {
+    pub fn new(kind: ResourceKind) -> Self {
+        Resource { kind, entered: AtomicBool::new(false), released: AtomicBool::new(false) }
+    }
+
+    /// Hand the resource to a `with` block, which releases it on exit.
+    pub fn enter(&self) {
+        self.entered.store(true, Ordering::SeqCst);
+    }
+
+    /// The value bound by `with ... as name`.
+    pub fn value(&self) -> Value {
+        match &self.kind {
+            ResourceKind::TempDir(p) => Value::Str(p.display().to_string()),
+            ResourceKind::EnvVars(_) => Value::Null,
+        }
+    }
+
+    /// Release the resource. Only the first call has any effect.
+    pub fn release(&self) -> Result<()> {
+        if self.released.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        match &self.kind {
+            ResourceKind::TempDir(p) => match std::fs::remove_dir_all(p) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                    Err(LatchError::IoError(format!("{}: {e}", p.display())))
+                }
+                _ => Ok(()),
+            },
+            ResourceKind::EnvVars(saved) => {
+                // SAFETY: see env.set() — only affects this process and its children.
+                for (key, old) in saved.iter().rev() {
+                    match old {
+                        Some(v) => unsafe { std::env::set_var(key, v) },
+                        None => unsafe { std::env::remove_var(key) },
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Drop for Resource {
+    /// Last resort for a `with` block whose exit never ran its release.
+    fn drop(&mut self) {
+        if self.entered.load(Ordering::SeqCst) {
+            let _ = self.release();
         }
     }
 }
@@ -223,12 +762,13 @@ impl Env {
                     guard[i] = val;
                     Ok(())
                 }
-                (Value::Map(map), Value::Str(key)) => {
-                    map.lock().unwrap().insert(key.clone(), val);
+                (Value::Map(map), key) => {
+                    let key = HashKey::from_value(key)?;
+                    map.lock().unwrap().insert(key, val);
                     Ok(())
                 }
                 _ => Err(LatchError::TypeMismatch {
-                    expected: "list[int] or dict[string]".into(),
+                    expected: "list[int] or dict".into(),
                     found: "incompatible types".into(),
                 }),
             }
@@ -51,6 +51,20 @@ pub enum LatchError {
     UndefinedFunction(String),
     UndeclaredAssign(String),
     ReturnOutsideFn,
+    PropagateOutsideFn,
+    /// `break`/`continue` (the keyword) with no enclosing loop
+    LoopControlOutsideLoop(String),
+    /// `break`/`continue` (the keyword) reaching a `parallel` body
+    LoopControlInParallel(String),
+    UndefinedLabel(String),
+    /// `Enum.Name` where the enum declares no such variant
+    UnknownVariant { enum_name: String, variant: String },
+    /// `match` over enum variants that leaves some variants unhandled
+    NonExhaustiveMatch { enum_name: String, missing: Vec<String> },
+    /// A class claims an interface without defining one of its methods
+    MissingInterfaceMethod { class: String, interface: String, method: String },
+    /// A class method can't be called the way its interface declares it
+    InterfaceMethodMismatch { class: String, interface: String, method: String, expected: usize },
     DuplicateFn(String),
     ArgCountMismatch { name: String, expected: usize, found: usize },
     TypeAnnotationMismatch { name: String, expected: Type, found: Type },
@@ -67,16 +81,19 @@ pub enum LatchError {
     ValueError(String),
     HttpError(String),
     AiError(String),
-    ProcessFailed { code: i32, stderr: String },
+    ProcessFailed { command: String, code: i32, stderr: String },
     DivisionByZero,
     IndexOutOfBounds { index: i64, len: usize },
     KeyNotFound(String),
+    TaskCancelled,
+    /// SIGINT/SIGTERM/SIGHUP with no `on_signal` trap; unwinds like `stop`
+    Interrupted(i32),
 
     // ── Internal signals (not user-facing) ───────────────────
     ReturnSignal(crate::env::Value),
     StopSignal(i32),
-    BreakSignal,
-    ContinueSignal,
+    BreakSignal(Option<String>),
+    ContinueSignal(Option<String>),
     YieldSignal(crate::env::Value),
 
     GenericError(String),
@@ -140,17 +157,31 @@ impl LatchError {
             Self::UnexpectedChar { .. } | Self::UnterminatedString { .. } => "Lexer Error",
             Self::UnexpectedToken { .. } | Self::UnexpectedEOF => "Parser Error",
             Self::UndefinedVariable(_) | Self::UndefinedFunction(_) |
-            Self::UndeclaredAssign(_) | Self::ReturnOutsideFn |
+            Self::UndeclaredAssign(_) | Self::ReturnOutsideFn | Self::PropagateOutsideFn |
+            Self::LoopControlOutsideLoop(_) | Self::LoopControlInParallel(_) | Self::UndefinedLabel(_) |
+            Self::UnknownVariant { .. } | Self::NonExhaustiveMatch { .. } |
+            Self::MissingInterfaceMethod { .. } | Self::InterfaceMethodMismatch { .. } |
             Self::DuplicateFn(_) | Self::ArgCountMismatch { .. } |
             Self::TypeAnnotationMismatch { .. } | Self::ImportNotFound(_) => "Semantic Error",
             Self::IoError(_) => "IO Error",
             Self::HttpError(_) => "HTTP Error",
             Self::AiError(_) => "AI Error",
             Self::ProcessFailed { .. } => "Process Error",
+            Self::Interrupted(_) => "Interrupted",
             _ => "Runtime Error",
         }
     }
 
+    /// Control flow travelling as an error (return, break, stop, cancellation…).
+    /// `catch` lets these pass through untouched.
+    pub fn is_control_signal(&self) -> bool {
+        matches!(
+            self,
+            Self::ReturnSignal(_) | Self::StopSignal(_) | Self::BreakSignal(_) |
+            Self::ContinueSignal(_) | Self::YieldSignal(_) | Self::TaskCancelled | Self::Interrupted(_)
+        )
+    }
+
     pub fn line_number(&self) -> Option<usize> {
         match self {
             Self::UnexpectedChar { line, .. } => Some(*line),
@@ -178,6 +209,18 @@ impl LatchError {
             Self::UndefinedFunction(n) => format!("Undefined function '{n}'"),
             Self::UndeclaredAssign(n) => format!("Assignment to undeclared variable '{n}'"),
             Self::ReturnOutsideFn => "'return' used outside of a function".into(),
+            Self::PropagateOutsideFn => "'?' used outside of a function".into(),
+            Self::LoopControlOutsideLoop(kw) => format!("'{kw}' used outside of a loop"),
+            Self::LoopControlInParallel(kw) => format!("'{kw}' cannot leave a 'parallel' body"),
+            Self::UndefinedLabel(l) => format!("No enclosing loop labeled '{l}'"),
+            Self::UnknownVariant { enum_name, variant } =>
+                format!("Enum '{enum_name}' has no variant '{variant}'"),
+            Self::NonExhaustiveMatch { enum_name, missing } =>
+                format!("Non-exhaustive match on '{enum_name}': missing {}", missing.join(", ")),
+            Self::MissingInterfaceMethod { class, interface, method } =>
+                format!("Class '{class}' implements '{interface}' but has no method '{method}'"),
+            Self::InterfaceMethodMismatch { class, interface, method, expected } =>
+                format!("'{class}.{method}' can't take the {expected} argument(s) that '{interface}.{method}' declares"),
             Self::DuplicateFn(n) => format!("Duplicate function definition '{n}'"),
             Self::ArgCountMismatch { name, expected, found } =>
                 format!("Function '{name}' expects {expected} argument(s), got {found}"),
@@ -194,14 +237,21 @@ impl LatchError {
             Self::ValueError(msg) => msg.clone(),
             Self::HttpError(msg) => msg.clone(),
             Self::AiError(msg) => msg.clone(),
-            Self::ProcessFailed { code, stderr } => format!("Process exited with code {code}: {stderr}"),
+            Self::ProcessFailed { command, code, stderr } if stderr.is_empty() => {
+                format!("Command '{command}' exited with code {code}")
+            }
+            Self::ProcessFailed { command, code, stderr } => {
+                format!("Command '{command}' exited with code {code}: {stderr}")
+            }
             Self::DivisionByZero => "Division by zero".into(),
             Self::IndexOutOfBounds { index, len } => format!("Index {index} out of bounds (length {len})"),
             Self::KeyNotFound(k) => format!("Key '{k}' not found in dict"),
+            Self::TaskCancelled => "Task was cancelled".into(),
+            Self::Interrupted(sig) => format!("Interrupted by {}", crate::runtime::signal::name(*sig)),
             Self::ReturnSignal(_) => "internal return signal".into(),
             Self::StopSignal(code) => format!("Script stopped with exit code {code}"),
-            Self::BreakSignal => "internal break signal".into(),
-            Self::ContinueSignal => "internal continue signal".into(),
+            Self::BreakSignal(_) => "internal break signal".into(),
+            Self::ContinueSignal(_) => "internal continue signal".into(),
             Self::YieldSignal(_) => "internal yield signal".into(),
             Self::GenericError(msg) => msg.clone(),
         }
@@ -217,6 +267,14 @@ impl LatchError {
             Self::UndefinedFunction(_) => "Define the function with 'fn name(...)' before calling it",
             Self::UndeclaredAssign(_) => "Declare the variable first with ':='",
             Self::ReturnOutsideFn => "'return' can only appear inside a 'fn' block",
+            Self::PropagateOutsideFn => "'?' returns errors from a 'fn'; at the top level use 'try?' or 'try/catch'",
+            Self::LoopControlOutsideLoop(_) => "'break' and 'continue' only work inside 'for' and 'while' loops",
+            Self::LoopControlInParallel(_) => "Every parallel iteration runs to completion; use 'return' inside a fn or filter the input instead",
+            Self::UndefinedLabel(_) => "Label the loop with 'name: for ...' or 'name: while ...'",
+            Self::UnknownVariant { .. } => "Check the variant names in the enum declaration",
+            Self::NonExhaustiveMatch { .. } => "Add an arm for each missing variant, or a '_' arm",
+            Self::MissingInterfaceMethod { .. } => "Define the method in the class or one of its parents",
+            Self::InterfaceMethodMismatch { .. } => "Match the method's parameters to the interface declaration",
             Self::DuplicateFn(_) => "Each function name must be unique in its scope",
             Self::ArgCountMismatch { .. } => "Check the function signature",
             Self::TypeAnnotationMismatch { .. } => "Change the annotation or the value",
@@ -226,6 +284,8 @@ impl LatchError {
             Self::AiError(_) => "Set LATCH_AI_KEY environment variable",
             Self::DivisionByZero => "Check the divisor is not zero",
             Self::IndexOutOfBounds { .. } => "Use len() to check bounds first",
+            Self::Interrupted(_) => "Use on_signal(\"SIGINT\", fn) to handle it, and 'finally' or 'defer' for cleanup",
+            Self::ProcessFailed { .. } => "Pass check=false to handle the exit code yourself, or use 'try' / 'or'",
             _ => "",
         }
     }
@@ -1,10 +1,15 @@
+use std::cmp;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
+use indexmap::{IndexMap, IndexSet};
 use rayon::prelude::*;
 
 use crate::ast::*;
-use crate::env::{Env, Value};
+use crate::env::{ClassDef, Dict, EnumDef, Env, HashKey, Instance, InterfaceDef, Method, Resource, TaskState, Value};
+use crate::output::{OrderedFlush, Output, OutputMode};
 use crate::error::{LatchError, Result};
 use crate::lexer::Lexer;
 use crate::parser::Parser;
@@ -13,27 +18,88 @@ use crate::runtime;
 /// Tree-walk interpreter — executes a checked AST.
 pub struct Interpreter {
     pub env: Env,
+    /// Set when running inside a `spawn`ed task; polled between statements.
+    cancel: Option<Arc<AtomicBool>>,
+    /// Where `print` writes; redirected per worker by `parallel ... output=`.
+    out: Output,
+    /// One list of pending cleanups per open scope (innermost last).
+    defers: Vec<Vec<Deferred>>,
+    /// `set strict` / `--strict`: a command exiting non-zero raises an error
+    strict: bool,
+    /// `on_signal` handlers by signal number, shared with workers
+    traps: Arc<Mutex<HashMap<i32, Value>>>,
+    /// A `parallel` worker or spawned task: leaves signals to the main thread
+    worker: bool,
+}
+
+/// Cleanup registered in a scope, run when that scope exits.
+enum Deferred {
+    /// `defer { ... }`
+    Block(Block),
+    /// Resource acquired by `with`
+    Release(Arc<Resource>),
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        Interpreter { env: Env::new() }
+        Interpreter {
+            env: Env::new(),
+            cancel: None,
+            out: Output::default(),
+            defers: Vec::new(),
+            strict: false,
+            traps: Arc::default(),
+            worker: false,
+        }
     }
 
-    pub fn with_env(env: Env) -> Self {
-        Interpreter { env }
+    /// Start in strict mode, as `latch run --strict` does.
+    pub fn strict(mut self) -> Self {
+        self.strict = true;
+        self
     }
 
-    pub fn run(&mut self, stmts: Vec<Stmt>) -> Result<()> {
-        for stmt in stmts {
-            self.exec_stmt(stmt)?;
+    /// Create a worker interpreter (parallel body, spawned task) that shares
+    /// this interpreter's runtime settings but runs in `env`.
+    fn fork(&self, env: Env) -> Interpreter {
+        Interpreter {
+            env,
+            cancel: self.cancel.clone(),
+            out: self.out.clone(),
+            defers: Vec::new(),
+            strict: self.strict,
+            traps: self.traps.clone(),
+            worker: true,
         }
-        Ok(())
+    }
+
+    pub fn run(&mut self, stmts: Vec<Stmt>) -> Result<()> {
+        // Top-level `defer`s run when the program (or `use`d file) finishes.
+        self.defers.push(Vec::new());
+        let result = self.exec_block_inner(stmts).and_then(|()| self.poll_signal());
+        self.run_deferred(result)
     }
 
     // ── Statements ───────────────────────────────────────────
 
     fn exec_stmt(&mut self, stmt: Stmt) -> Result<()> {
+        if let Some(flag) = &self.cancel {
+            if flag.load(Ordering::SeqCst) {
+                return Err(LatchError::TaskCancelled);
+            }
+        }
+        self.poll_signal()?;
+
+        let result = self.exec_stmt_inner(stmt);
+        if let Err(LatchError::Interrupted(_)) = result {
+            // However it was raised (here, in a blocking call or a worker),
+            // the signal has been acted on: cleanup code mustn't raise it again
+            runtime::signal::mark_seen();
+        }
+        result
+    }
+
+    fn exec_stmt_inner(&mut self, stmt: Stmt) -> Result<()> {
         match stmt {
             Stmt::Let { name, value, .. } => {
                 let val = self.eval_expr(value)?;
@@ -63,23 +129,41 @@ impl Interpreter {
                             }
                             guard[i] = val;
                         }
-                        (Value::Map(map), Value::Str(key)) => {
-                            map.lock().unwrap().insert(key.clone(), val);
+                        (Value::Map(map), key) => {
+                            let key = HashKey::from_value(key)?;
+                            map.lock().unwrap().insert(key, val);
                         }
                         _ => return Err(LatchError::TypeMismatch {
-                            expected: "list[int] or dict[string]".into(),
+                            expected: "list[int] or dict".into(),
                             found: "incompatible types".into(),
                         }),
                     }
                 }
             }
 
+            Stmt::FieldAssign { target, field, value } => {
+                let obj = self.eval_expr(target)?;
+                let val = self.eval_expr(value)?;
+                match obj {
+                    Value::Instance(obj) => {
+                        obj.fields.lock().unwrap().insert(field, val);
+                    }
+                    Value::Map(map) => {
+                        map.lock().unwrap().insert(HashKey::from(field.as_str()), val);
+                    }
+                    other => return Err(LatchError::TypeMismatch {
+                        expected: "object or dict".into(),
+                        found: other.type_name().into(),
+                    }),
+                }
+            }
+
             Stmt::CompoundAssign { name, op, value } => {
                 let current = self.env.get(&name)
                     .cloned()
                     .ok_or_else(|| LatchError::UndefinedVariable(name.clone()))?;
                 let rhs = self.eval_expr(value)?;
-                let result = self.eval_binop(op, current, rhs)?;
+                let result = self.binop(op, current, rhs)?;
                 self.env.assign(&name, result)?;
             }
 
@@ -106,50 +190,37 @@ impl Interpreter {
                 }
             }
 
-            Stmt::For { var, iter, body } => {
-                let list = self.eval_expr(iter)?.into_list()?;
+            Stmt::For { label, var, iter, body, else_body } => {
+                let iterable = self.eval_expr(iter)?;
+                let list = self.iterate(iterable)?;
+                let mut broke = false;
                 for item in list {
-                    let parent = std::mem::replace(&mut self.env, Env::new());
-                    self.env = parent.child();
+                    self.push_scope();
                     self.env.set(&var, item);
-                    
+
                     // Execute body with break/continue handling
-                    let mut should_continue = false;
-                    for s in &body {
-                        match self.exec_stmt(s.clone()) {
-                            Ok(()) => {}
-                            Err(LatchError::BreakSignal) => {
-                                let child = std::mem::replace(&mut self.env, Env::new());
-                                self.env = child.into_parent().unwrap();
-                                return Ok(());
-                            }
-                            Err(LatchError::ContinueSignal) => {
-                                should_continue = true;
-                                break;
-                            }
-                            Err(e) => {
-                                let child = std::mem::replace(&mut self.env, Env::new());
-                                self.env = child.into_parent().unwrap();
-                                return Err(e);
-                            }
-                        }
-                    }
-                    
-                    let child = std::mem::replace(&mut self.env, Env::new());
-                    self.env = child.into_parent().unwrap();
-                    
-                    if should_continue {
-                        continue;
+                    let result = self.exec_block_inner(body.clone());
+                    if loop_control(self.pop_scope(result), &label)? == LoopFlow::Break {
+                        broke = true;
+                        break;
                     }
                 }
+                if let (false, Some(else_body)) = (broke, else_body) {
+                    self.exec_block(else_body)?;
+                }
             }
 
-            Stmt::Parallel { var, iter, workers, body } => {
-                let list = self.eval_expr(iter)?.into_list()?;
+            Stmt::Parallel { var, iter, workers, output, body } => {
+                let iterable = self.eval_expr(iter)?;
+                let list = self.iterate(iterable)?;
                 let worker_count = match workers {
                     Some(w) => Some(self.eval_expr(w)?.as_int()? as usize),
                     None => None,
                 };
+                let mode = match output {
+                    Some(o) => OutputMode::parse(self.eval_expr(o)?.as_str()?)?,
+                    None => OutputMode::Interleaved,
+                };
 
                 let pool = match worker_count {
                     Some(n) => rayon::ThreadPoolBuilder::new()
@@ -163,26 +234,34 @@ impl Interpreter {
 
                 let env_snapshot = self.env.clone();
                 let body_clone = body.clone();
+                let signals_seen = runtime::signal::seen();
 
                 // Deterministic parallel: ALL workers run to completion.
                 // Errors are collected; the first error is propagated after
                 // every worker has finished. No early cancellation.
+                let ordered = OrderedFlush::new(list.len());
                 let results: Vec<std::result::Result<(), LatchError>> = pool.install(|| {
                     list.into_par_iter()
-                        .map(|item| {
+                        .enumerate()
+                        .map(|(i, item)| {
+                            runtime::signal::set_seen(signals_seen);
+                            let out = self.out.for_worker(mode, &item.to_string());
                             let mut child_env = env_snapshot.clone().child();
                             child_env.set(&var, item);
-                            let mut interp = Interpreter::with_env(child_env);
-                            interp.run(body_clone.clone())
+                            let mut interp = self.fork(child_env);
+                            interp.out = out.clone();
+                            let result = interp.run(body_clone.clone());
+                            if mode == OutputMode::Ordered {
+                                ordered.finish(i, out);
+                            }
+                            result
                         })
                         .collect()
                 });
 
                 // Propagate the first error (if any) after all workers finished
                 for result in results {
-                    if let Err(e) = result {
-                        return Err(e);
-                    }
+                    result?;
                 }
             }
 
@@ -198,46 +277,26 @@ impl Interpreter {
 
             Stmt::Try { body, catch_var, catch_body, finally_body } => {
                 // Execute body in its own scope
-                let parent = std::mem::replace(&mut self.env, Env::new());
-                self.env = parent.child();
-
+                self.push_scope();
                 let result = self.exec_block_inner(body);
-
-                let child = std::mem::replace(&mut self.env, Env::new());
-                self.env = child.into_parent().unwrap();
-
-                let catch_result = if let Err(e) = result {
-                    // Don't catch return signals
-                    if matches!(e, LatchError::ReturnSignal(_)) {
-                        // Execute finally before returning
-                        if let Some(finally_block) = finally_body {
-                            let _ = self.exec_block_inner(finally_block);
-                        }
-                        return Err(e);
-                    }
-                    let parent = std::mem::replace(&mut self.env, Env::new());
-                    self.env = parent.child();
-                    self.env.set(&catch_var, Value::Str(format!("{e}")));
-                    let res = self.exec_block_inner(catch_body);
-                    let child = std::mem::replace(&mut self.env, Env::new());
-                    self.env = child.into_parent().unwrap();
-                    res
-                } else {
-                    Ok(())
+                let result = self.pop_scope(result);
+
+                let catch_result = match result {
+                    // return/break/continue/stop and cancellation are not
+                    // errors: they pass through `catch` (finally still runs)
+                    Err(e) if !e.is_control_signal() => {
+                        self.push_scope();
+                        self.env.set(&catch_var, Value::Str(format!("{e}")));
+                        let res = self.exec_block_inner(catch_body);
+                        self.pop_scope(res)
+                    }
+                    other => other,
                 };
 
                 // Execute finally block if present
                 if let Some(finally_block) = finally_body {
-                    let parent = std::mem::replace(&mut self.env, Env::new());
-                    self.env = parent.child();
-                    let finally_result = self.exec_block_inner(finally_block);
-                    let child = std::mem::replace(&mut self.env, Env::new());
-                    self.env = child.into_parent().unwrap();
-                    
                     // Finally errors override catch results
-                    if finally_result.is_err() {
-                        return finally_result;
-                    }
+                    self.exec_block(finally_block)?;
                 }
 
                 catch_result?;
@@ -270,54 +329,123 @@ impl Interpreter {
                 return Err(LatchError::StopSignal(code));
             }
 
-            Stmt::While { cond, body } => {
+            Stmt::SetOption { name, value } => {
+                let on = self.eval_expr(value)?.as_bool()?;
+                match name.as_str() {
+                    "strict" => self.strict = on,
+                    _ => return Err(LatchError::GenericError(format!("unknown option '{name}'"))),
+                }
+            }
+
+            Stmt::While { label, cond, body, else_body } => {
+                let mut broke = false;
                 loop {
                     let val = self.eval_expr(cond.clone())?;
                     if !val.is_truthy() {
                         break;
                     }
                     // Execute body in its own scope
-                    let parent = std::mem::replace(&mut self.env, Env::new());
-                    self.env = parent.child();
-                    for s in &body {
-                        match self.exec_stmt(s.clone()) {
-                            Ok(()) => {}
-                            Err(LatchError::BreakSignal) => {
-                                let child = std::mem::replace(&mut self.env, Env::new());
-                                self.env = child.into_parent().unwrap();
-                                return Ok(());
-                            }
-                            Err(LatchError::ContinueSignal) => {
-                                break;
-                            }
-                            Err(e) => {
-                                let child = std::mem::replace(&mut self.env, Env::new());
-                                self.env = child.into_parent().unwrap();
-                                return Err(e);
-                            }
-                        }
+                    self.push_scope();
+                    let result = self.exec_block_inner(body.clone());
+                    if loop_control(self.pop_scope(result), &label)? == LoopFlow::Break {
+                        broke = true;
+                        break;
                     }
-                    let child = std::mem::replace(&mut self.env, Env::new());
-                    self.env = child.into_parent().unwrap();
                 }
+                if let (false, Some(else_body)) = (broke, else_body) {
+                    self.exec_block(else_body)?;
+                }
+            }
+
+            Stmt::Defer(body) => {
+                self.defer(Deferred::Block(body))?;
+            }
+
+            Stmt::With { resource, name, body } => {
+                let val = self.eval_expr(resource)?;
+                self.push_scope();
+                // Registered first, so it runs after any `defer` in the body.
+                let bound = match &val {
+                    Value::Resource(r) => {
+                        r.enter();
+                        self.defer(Deferred::Release(r.clone()))?;
+                        r.value()
+                    }
+                    other => other.clone(),
+                };
+                if let Some(name) = name {
+                    self.env.set(&name, bound);
+                }
+                let result = self.exec_block_inner(body);
+                self.pop_scope(result)?;
             }
 
-            Stmt::Break => {
-                return Err(LatchError::BreakSignal);
+            Stmt::Break(label) => {
+                return Err(LatchError::BreakSignal(label));
             }
 
-            Stmt::Continue => {
-                return Err(LatchError::ContinueSignal);
+            Stmt::Continue(label) => {
+                return Err(LatchError::ContinueSignal(label));
             }
 
             Stmt::Expr(expr) => {
                 self.eval_expr(expr)?;
             }
 
-            Stmt::Class { name, fields: _, methods: _ } => {
-                // Store class info in environment as a special value
-                let class_info = Value::Str(format!("<class {}>", name));
-                self.env.set(&name, class_info);
+            Stmt::Enum { name, variants } => {
+                let def = EnumDef {
+                    name: name.clone(),
+                    variants: variants.into_iter().map(|v| (v.name, v.fields)).collect(),
+                };
+                self.env.set(&name, Value::EnumType(Arc::new(def)));
+            }
+
+            Stmt::Class { name, parent, interfaces, fields, methods } => {
+                let parent = match parent {
+                    Some(p) => match self.env.get(&p) {
+                        Some(Value::Class(def)) => Some(def.clone()),
+                        Some(other) => return Err(LatchError::TypeMismatch {
+                            expected: "class to extend".into(),
+                            found: other.type_name().into(),
+                        }),
+                        None => return Err(LatchError::UndefinedVariable(p)),
+                    },
+                    None => None,
+                };
+                let interfaces = interfaces.into_iter().map(|i| match self.env.get(&i) {
+                    Some(Value::Interface(def)) => Ok(def.clone()),
+                    Some(other) => Err(LatchError::TypeMismatch {
+                        expected: "interface to implement".into(),
+                        found: other.type_name().into(),
+                    }),
+                    None => Err(LatchError::UndefinedVariable(i)),
+                }).collect::<Result<Vec<_>>>()?;
+                let def = Arc::new(ClassDef {
+                    name: name.clone(),
+                    parent,
+                    interfaces,
+                    fields: fields.into_iter().map(|(f, _, default)| (f, default)).collect(),
+                    methods: methods.into_iter()
+                        .map(|(m, params, body)| (m, Method { params, body }))
+                        .collect(),
+                });
+                // Interfaces from other files aren't seen by the analyzer
+                for iface in &def.interfaces {
+                    for (method, _) in &iface.methods {
+                        if def.find_method(method).is_none() {
+                            return Err(LatchError::MissingInterfaceMethod {
+                                class: name.clone(),
+                                interface: iface.name.clone(),
+                                method: method.clone(),
+                            });
+                        }
+                    }
+                }
+                self.env.set(&name, Value::Class(def));
+            }
+
+            Stmt::Interface { name, methods } => {
+                self.env.set(&name, Value::Interface(Arc::new(InterfaceDef { name: name.clone(), methods })));
             }
 
             Stmt::Export(names) => {
@@ -344,9 +472,21 @@ impl Interpreter {
         Ok(())
     }
 
-    /// Public wrapper for REPL: execute a single statement.
+    /// Public wrapper for REPL: execute a single statement. A `defer` joins
+    /// the session's top-level list, which `end_repl` runs.
     pub fn exec_stmt_public(&mut self, stmt: Stmt) -> Result<()> {
-        self.exec_stmt(stmt)
+        if self.defers.is_empty() {
+            self.defers.push(Vec::new());
+        }
+        self.exec_stmt(stmt).and_then(|()| self.poll_signal())
+    }
+
+    /// REPL helper: run the session's top-level defers as it exits.
+    pub fn end_repl(&mut self) -> Result<()> {
+        if self.defers.is_empty() {
+            return Ok(());
+        }
+        self.run_deferred(Ok(()))
     }
 
     /// REPL helper: evaluate an expression statement and return its value.
@@ -360,21 +500,65 @@ impl Interpreter {
                 }
             }
             other => {
-                self.exec_stmt(other)?;
+                self.run(vec![other])?;
                 Ok(None)
             }
         }
     }
 
     fn exec_block(&mut self, block: Block) -> Result<()> {
+        self.push_scope();
+        let result = self.exec_block_inner(block);
+        self.pop_scope(result)
+    }
+
+    /// Enter a child scope with its own variables and `defer` list.
+    fn push_scope(&mut self) {
         let parent = std::mem::replace(&mut self.env, Env::new());
         self.env = parent.child();
-        let result = self.exec_block_inner(block);
+        self.defers.push(Vec::new());
+    }
+
+    /// Leave the current scope: run its deferred cleanups, then drop its variables.
+    fn pop_scope(&mut self, result: Result<()>) -> Result<()> {
+        let result = self.run_deferred(result);
         let child = std::mem::replace(&mut self.env, Env::new());
         self.env = child.into_parent().unwrap();
         result
     }
 
+    fn defer(&mut self, action: Deferred) -> Result<()> {
+        match self.defers.last_mut() {
+            Some(frame) => {
+                frame.push(action);
+                Ok(())
+            }
+            None => Err(LatchError::GenericError("defer: no enclosing scope".into())),
+        }
+    }
+
+    /// Pop the innermost `defer` list and run it, most recent first.
+    /// A cleanup error replaces a successful `result`; an error or signal
+    /// already in flight wins over cleanup errors.
+    fn run_deferred(&mut self, mut result: Result<()>) -> Result<()> {
+        let deferred = self.defers.pop().unwrap_or_default();
+        // Cleanups must run even in a cancelled task.
+        let cancel = self.cancel.take();
+        for action in deferred.into_iter().rev() {
+            let outcome = match action {
+                Deferred::Block(body) => self.exec_block(body),
+                Deferred::Release(resource) => resource.release(),
+            };
+            if let Err(e) = outcome {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        self.cancel = cancel;
+        result
+    }
+
     fn exec_block_inner(&mut self, block: Block) -> Result<()> {
         for stmt in block {
             self.exec_stmt(stmt)?;
@@ -388,21 +572,51 @@ impl Interpreter {
         match expr {
             Expr::Int(n)   => Ok(Value::Int(n)),
             Expr::Float(n) => Ok(Value::Float(n)),
+            Expr::Duration(ms) => runtime::time::millis(ms).map(Value::Duration),
             Expr::Bool(b)  => Ok(Value::Bool(b)),
             Expr::Str(s)   => Ok(Value::Str(s)),
             Expr::Null     => Ok(Value::Null),
 
             Expr::List(items) => {
-                let vals: Vec<Value> = items.into_iter()
-                    .map(|e| self.eval_expr(e))
-                    .collect::<Result<_>>()?;
+                let mut vals = Vec::new();
+                for item in items {
+                    match item {
+                        Expr::Spread(inner) => vals.extend(self.eval_expr(*inner)?.into_list()?),
+                        other => vals.push(self.eval_expr(other)?),
+                    }
+                }
                 Ok(Value::new_list(vals))
             }
 
+            Expr::Set(items) => {
+                let mut keys = Vec::new();
+                for item in items {
+                    keys.push(HashKey::from_value(&self.eval_expr(item)?)?);
+                }
+                Ok(Value::new_set(keys))
+            }
+
             Expr::Map(entries) => {
-                let mut map = HashMap::new();
-                for (key, val_expr) in entries {
-                    map.insert(key, self.eval_expr(val_expr)?);
+                let mut map = Dict::new();
+                for entry in entries {
+                    match entry {
+                        MapEntry::Pair(key, val_expr) => {
+                            let key = HashKey::from_value(&self.eval_expr(key)?)?;
+                            map.insert(key, self.eval_expr(val_expr)?);
+                        }
+                        // Later entries win, so `{...defaults, ...overrides}` merges
+                        MapEntry::Spread(expr) => match self.eval_expr(expr)? {
+                            Value::Map(other) => {
+                                for (k, v) in other.lock().unwrap().iter() {
+                                    map.insert(k.clone(), v.clone());
+                                }
+                            }
+                            other => return Err(LatchError::TypeMismatch {
+                                expected: "dict".into(),
+                                found: other.type_name().into(),
+                            }),
+                        },
+                    }
                 }
                 Ok(Value::new_map(map))
             }
@@ -430,7 +644,32 @@ impl Interpreter {
                             // Evaluate the first (and only) expression statement
                             if let Some(Stmt::Expr(e)) = expr.into_iter().next() {
                                 let val = self.eval_expr(e)?;
-                                result.push_str(&format!("{val}"));
+                                result.push_str(&self.display(&val)?);
+                            }
+                        }
+                    }
+                }
+                Ok(Value::Str(result))
+            }
+
+            Expr::ShellCommand(parts) => {
+                let mut result = String::new();
+                for part in parts {
+                    match part {
+                        StringPart::Literal(s) => result.push_str(&s),
+                        StringPart::Expr(tokens) => {
+                            let mut parser = Parser::new(tokens);
+                            let expr = parser.parse_program()?;
+                            if let Some(Stmt::Expr(e)) = expr.into_iter().next() {
+                                let val = self.eval_expr(e)?;
+                                let words = match &val {
+                                    Value::List(items) => items.lock().unwrap().clone(),
+                                    _ => vec![val],
+                                };
+                                let words = words.iter()
+                                    .map(|w| Ok(runtime::proc::quote(&self.display(w)?)))
+                                    .collect::<Result<Vec<_>>>()?;
+                                result.push_str(&words.join(" "));
                             }
                         }
                     }
@@ -441,7 +680,7 @@ impl Interpreter {
             Expr::BinOp { op, left, right } => {
                 let l = self.eval_expr(*left)?;
                 let r = self.eval_expr(*right)?;
-                self.eval_binop(op, l, r)
+                self.binop(op, l, r)
             }
 
             Expr::UnaryOp { op, expr } => {
@@ -450,6 +689,7 @@ impl Interpreter {
                     UnaryOp::Neg => match val {
                         Value::Int(n)   => Ok(Value::Int(-n)),
                         Value::Float(n) => Ok(Value::Float(-n)),
+                        Value::Duration(d) => Ok(Value::Duration(-d)),
                         _ => Err(LatchError::TypeMismatch {
                             expected: "number".into(),
                             found: val.type_name().into(),
@@ -459,31 +699,70 @@ impl Interpreter {
                 }
             }
 
-            Expr::Call { name, args, kwargs: _ } => {
-                let evaluated: Vec<Value> = args.into_iter()
-                    .map(|a| self.eval_expr(a))
-                    .collect::<Result<_>>()?;
-                self.call_function(&name, evaluated)
+            Expr::Call { name, args, kwargs } => {
+                let (args, kwargs) = self.eval_args(args, kwargs)?;
+                self.call_function(&name, args, kwargs)
             }
 
-            Expr::ModuleCall { module, method, args } => {
-                let evaluated: Vec<Value> = args.into_iter()
-                    .map(|a| self.eval_expr(a))
-                    .collect::<Result<_>>()?;
+            Expr::ModuleCall { module, method, args, kwargs } => {
+                let (mut args, kwargs) = self.eval_args(args, kwargs)?;
+                // Outcome.Failed(2, "boom") — construct a payload variant
+                match self.env.get(&module) {
+                    Some(Value::EnumType(def)) => return def.clone().construct(&method, args),
+                    Some(Value::Instance(obj)) => {
+                        let obj = obj.clone();
+                        return self.call_method(&obj, &obj.class, &method, args, kwargs);
+                    }
+                    Some(Value::Process(handle)) => {
+                        let handle = handle.clone();
+                        if !kwargs.is_empty() {
+                            args.push(kwargs_dict(kwargs));
+                        }
+                        return handle.call(&method, args);
+                    }
+                    _ => {}
+                }
+                // super.method(...) — start the lookup above the class defining
+                // the running method
+                if module == "super" {
+                    if let (Some(Value::Instance(obj)), Some(Value::Class(owner))) =
+                        (self.env.get("self"), self.env.get(SUPER_CLASS))
+                    {
+                        let (obj, owner) = (obj.clone(), owner.clone());
+                        let parent = owner.parent.clone().ok_or_else(|| LatchError::GenericError(
+                            format!("'super' used in class '{}', which has no parent", owner.name),
+                        ))?;
+                        return self.call_method(&obj, &parent, &method, args, kwargs);
+                    }
+                }
+                if !kwargs.is_empty() {
+                    args.push(kwargs_dict(kwargs));
+                }
+                self.call_module(&module, &method, args)
+            }
 
-                match module.as_str() {
-                    "fs"   => runtime::fs::call(&method, evaluated),
-                    "proc" => runtime::proc::call(&method, evaluated),
-                    "http" => runtime::http::call(&method, evaluated),
-                    "time" => runtime::time::call(&method, evaluated),
-                    "ai"   => runtime::ai::call(&method, evaluated),
-                    "json" => runtime::json::call(&method, evaluated),
-                    "env"  => runtime::env::call(&method, evaluated),
-                    "path" => runtime::path::call(&method, evaluated),
-                    _ => Err(LatchError::UnknownModule(module)),
+            Expr::MethodCall { object, method, args, kwargs } => {
+                let object = self.eval_expr(*object)?;
+                let (mut args, kwargs) = self.eval_args(args, kwargs)?;
+                match object {
+                    Value::Instance(obj) => self.call_method(&obj, &obj.class, &method, args, kwargs),
+                    Value::Process(handle) => {
+                        if !kwargs.is_empty() {
+                            args.push(kwargs_dict(kwargs));
+                        }
+                        handle.call(&method, args)
+                    }
+                    other => Err(LatchError::TypeMismatch {
+                        expected: format!("object with a '{method}' method"),
+                        found: other.type_name().into(),
+                    }),
                 }
             }
 
+            Expr::Spread(_) => Err(LatchError::GenericError(
+                "`...` is only allowed in call arguments and list or dict literals".into(),
+            )),
+
             Expr::Index { expr, index } => {
                 let container = self.eval_expr(*expr)?;
                 let idx = self.eval_expr(*index)?;
@@ -498,14 +777,28 @@ impl Interpreter {
                             Ok(guard[i as usize].clone())
                         }
                     }
-                    (Value::Map(map), Value::Str(key)) => {
+                    (Value::Map(map), key) => {
                         let guard = map.lock().unwrap();
-                        guard.get(key)
+                        guard.get(&HashKey::from_value(key)?)
                             .cloned()
-                            .ok_or(LatchError::KeyNotFound(key.clone()))
+                            .ok_or_else(|| LatchError::KeyNotFound(key.to_string()))
+                    }
+                    (Value::Bytes(data), Value::Int(i)) => {
+                        let i = *i;
+                        if i < 0 || i as usize >= data.len() {
+                            Err(LatchError::IndexOutOfBounds { index: i, len: data.len() })
+                        } else {
+                            Ok(Value::Int(data[i as usize] as i64))
+                        }
+                    }
+                    // Strings index by Unicode scalar; negative indices count from the end
+                    (Value::Str(s), Value::Int(i)) => {
+                        let len = s.chars().count();
+                        let pos = resolve_index(*i, len)?;
+                        Ok(Value::Str(s.chars().nth(pos).unwrap().to_string()))
                     }
                     _ => Err(LatchError::TypeMismatch {
-                        expected: "list[int] or dict[string]".into(),
+                        expected: "list[int], string[int], bytes[int] or dict".into(),
                         found: format!("{}[{}]", container.type_name(), idx.type_name()),
                     }),
                 }
@@ -514,35 +807,22 @@ impl Interpreter {
             Expr::FieldAccess { expr, field } => {
                 let val = self.eval_expr(*expr)?;
                 match val {
-                    Value::ProcessResult { stdout, stderr, code } => {
-                        match field.as_str() {
-                            "stdout" => Ok(Value::Str(stdout)),
-                            "stderr" => Ok(Value::Str(stderr)),
-                            "code"   => Ok(Value::Int(code as i64)),
-                            _ => Err(LatchError::KeyNotFound(field)),
-                        }
-                    }
-                    Value::HttpResponse { status, body, headers } => {
-                        match field.as_str() {
-                            "status"  => Ok(Value::Int(status)),
-                            "body"    => Ok(Value::Str(body)),
-                            "headers" => {
-                                let map: HashMap<String, Value> = headers.into_iter()
-                                    .map(|(k, v)| (k, Value::Str(v)))
-                                    .collect();
-                                Ok(Value::new_map(map))
-                            }
-                            _ => Err(LatchError::KeyNotFound(field)),
-                        }
+                    Value::ProcessResult(_) | Value::HttpResponse { .. } | Value::DateTime(_) | Value::Duration(_)
+                    | Value::Result(_) | Value::Enum(_) | Value::Process(_) => {
+                        record_field(&val, &field).ok_or(LatchError::KeyNotFound(field))
                     }
+                    // Env.Dev — a variant without payload
+                    Value::EnumType(def) => def.construct(&field, Vec::new()),
+                    Value::Instance(obj) => obj.field(&field)
+                        .ok_or_else(|| LatchError::KeyNotFound(format!("{}.{field}", obj.class.name))),
                     Value::Map(map) => {
                         let guard = map.lock().unwrap();
-                        guard.get(&field)
+                        guard.get(&HashKey::from(field.as_str()))
                             .cloned()
                             .ok_or(LatchError::KeyNotFound(field))
                     }
                     _ => Err(LatchError::TypeMismatch {
-                        expected: "dict, response, or process result".into(),
+                        expected: "dict, object, response, process result, datetime, duration, result or enum".into(),
                         found: val.type_name().into(),
                     }),
                 }
@@ -550,11 +830,49 @@ impl Interpreter {
 
             Expr::OrDefault { expr, default } => {
                 match self.eval_expr(*expr) {
+                    // An `err` result counts as failure; an `ok` one unwraps
+                    Ok(Value::Result(Ok(val))) => Ok(*val),
+                    Ok(Value::Result(Err(_))) => self.eval_expr(*default),
                     Ok(val) => Ok(val),
+                    Err(e) if e.is_control_signal() => Err(e),
                     Err(_) => self.eval_expr(*default),
                 }
             }
 
+            Expr::Match { subject, arms } => {
+                let subject = self.eval_expr(*subject)?;
+                for arm in &arms {
+                    for pattern in &arm.patterns {
+                        let Some(bindings) = self.match_pattern(pattern, &subject)? else {
+                            continue;
+                        };
+                        if let Some(value) = self.run_match_arm(arm, bindings)? {
+                            return Ok(value);
+                        }
+                    }
+                }
+                Err(LatchError::ValueError(format!("no match arm for {subject}")))
+            }
+
+            Expr::TryResult(expr) => {
+                match self.eval_expr(*expr) {
+                    Ok(val @ Value::Result(_)) => Ok(val),
+                    Ok(val) => Ok(Value::Result(Ok(Box::new(val)))),
+                    Err(e) if e.is_control_signal() => Err(e),
+                    Err(e) => Ok(Value::err_from(&e)),
+                }
+            }
+
+            Expr::Propagate(expr) => {
+                match self.eval_expr(*expr) {
+                    Ok(Value::Result(Ok(val))) => Ok(*val),
+                    Ok(err @ Value::Result(Err(_))) => Err(LatchError::ReturnSignal(err)),
+                    Ok(val) => Ok(val),
+                    Err(e) if e.is_control_signal() => Err(e),
+                    Err(e) => Err(LatchError::ReturnSignal(Value::err_from(&e))),
+                }
+            }
+
             Expr::NullCoalesce { expr, default } => {
                 let val = self.eval_expr(*expr)?;
                 if matches!(val, Value::Null) {
@@ -575,30 +893,19 @@ impl Interpreter {
                 let val = self.eval_expr(*expr)?;
                 // func is a Call expression — inject val as first argument
                 match *func {
-                    Expr::Call { name, mut args, kwargs: _ } => {
+                    Expr::Call { name, args, kwargs } => {
                         // Evaluate existing args, then prepend the piped value
-                        let mut evaluated = vec![val];
-                        for a in args.drain(..) {
-                            evaluated.push(self.eval_expr(a)?);
-                        }
-                        self.call_function(&name, evaluated)
+                        let (mut evaluated, kwargs) = self.eval_args(args, kwargs)?;
+                        evaluated.insert(0, val);
+                        self.call_function(&name, evaluated, kwargs)
                     }
-                    Expr::ModuleCall { module, method, mut args } => {
-                        let mut evaluated = vec![val];
-                        for a in args.drain(..) {
-                            evaluated.push(self.eval_expr(a)?);
-                        }
-                        match module.as_str() {
-                            "fs"   => runtime::fs::call(&method, evaluated),
-                            "proc" => runtime::proc::call(&method, evaluated),
-                            "http" => runtime::http::call(&method, evaluated),
-                            "time" => runtime::time::call(&method, evaluated),
-                            "ai"   => runtime::ai::call(&method, evaluated),
-                            "json" => runtime::json::call(&method, evaluated),
-                            "env"  => runtime::env::call(&method, evaluated),
-                            "path" => runtime::path::call(&method, evaluated),
-                            _ => Err(LatchError::UnknownModule(module)),
+                    Expr::ModuleCall { module, method, args, kwargs } => {
+                        let (mut evaluated, kwargs) = self.eval_args(args, kwargs)?;
+                        evaluated.insert(0, val);
+                        if !kwargs.is_empty() {
+                            evaluated.push(kwargs_dict(kwargs));
                         }
+                        self.call_module(&module, &method, evaluated)
                     }
                     Expr::Fn { params, body } => {
                         // Pipe into anonymous function — call inline, no capture
@@ -625,28 +932,12 @@ impl Interpreter {
                     Value::Null => Ok(Value::Null),
                     Value::Map(map) => {
                         let guard = map.lock().unwrap();
-                        Ok(guard.get(&field).cloned().unwrap_or(Value::Null))
-                    }
-                    Value::HttpResponse { status, body, headers } => {
-                        match field.as_str() {
-                            "status"  => Ok(Value::Int(status)),
-                            "body"    => Ok(Value::Str(body)),
-                            "headers" => {
-                                let map: HashMap<String, Value> = headers.into_iter()
-                                    .map(|(k, v)| (k, Value::Str(v)))
-                                    .collect();
-                                Ok(Value::new_map(map))
-                            }
-                            _ => Ok(Value::Null),
-                        }
+                        Ok(guard.get(&HashKey::from(field)).cloned().unwrap_or(Value::Null))
                     }
-                    Value::ProcessResult { stdout, stderr, code } => {
-                        match field.as_str() {
-                            "stdout" => Ok(Value::Str(stdout)),
-                            "stderr" => Ok(Value::Str(stderr)),
-                            "code"   => Ok(Value::Int(code as i64)),
-                            _ => Ok(Value::Null),
-                        }
+                    Value::Instance(obj) => Ok(obj.field(&field).unwrap_or(Value::Null)),
+                    Value::ProcessResult(_) | Value::HttpResponse { .. } | Value::DateTime(_) | Value::Duration(_)
+                    | Value::Result(_) | Value::Enum(_) | Value::Process(_) => {
+                        Ok(record_field(&val, &field).unwrap_or(Value::Null))
                     }
                     _ => Ok(Value::Null),
                 }
@@ -661,80 +952,93 @@ impl Interpreter {
                 }
             }
 
-            Expr::ListComp { body, var, iter, cond } => {
-                let iterable = self.eval_expr(*iter)?;
-                let items = iterable.into_list()?;
+            Expr::ListComp { body, clauses } => {
                 let mut result = Vec::new();
-                
-                for item in items {
-                    // Create new scope for the comprehension
-                    let parent = std::mem::replace(&mut self.env, Env::new());
-                    self.env = parent.child();
-                    
-                    // Set loop variable
-                    self.env.set(&var, item);
-                    
-                    // Check condition if present
-                    let include = if let Some(ref c) = cond {
-                        self.eval_expr(*c.clone())?.is_truthy()
-                    } else {
-                        true
-                    };
-                    
-                    if include {
-                        let val = self.eval_expr(*body.clone())?;
-                        result.push(val);
-                    }
-                    
-                    // Restore parent scope
-                    let child = std::mem::replace(&mut self.env, Env::new());
-                    self.env = child.into_parent().unwrap();
-                }
-                
+                self.run_comprehension(&clauses, &mut |interp| {
+                    result.push(interp.eval_expr((*body).clone())?);
+                    Ok(())
+                })?;
                 Ok(Value::new_list(result))
             }
 
-            Expr::Slice { expr, start, end } => {
+            Expr::SetComp { body, clauses } => {
+                let mut result = IndexSet::new();
+                self.run_comprehension(&clauses, &mut |interp| {
+                    result.insert(HashKey::from_value(&interp.eval_expr((*body).clone())?)?);
+                    Ok(())
+                })?;
+                Ok(Value::new_set(result))
+            }
+
+            Expr::DictComp { key, value, clauses } => {
+                let mut result = Dict::new();
+                self.run_comprehension(&clauses, &mut |interp| {
+                    let k = HashKey::from_value(&interp.eval_expr((*key).clone())?)?;
+                    let v = interp.eval_expr((*value).clone())?;
+                    result.insert(k, v);
+                    Ok(())
+                })?;
+                Ok(Value::new_map(result))
+            }
+
+            Expr::Slice { expr, start, end, step } => {
                 let list_val = self.eval_expr(*expr)?;
+                let mut bound = |e: Option<Box<Expr>>| -> Result<Option<i64>> {
+                    match e {
+                        Some(e) => Ok(Some(self.eval_expr(*e)?.as_int()?)),
+                        None => Ok(None),
+                    }
+                };
+                let (start, end, step) = (bound(start)?, bound(end)?, bound(step)?);
                 match list_val {
                     Value::List(list) => {
                         let guard = list.lock().unwrap();
-                        let len = guard.len() as i64;
-                        
-                        // Evaluate start index
-                        let start_idx = match start {
-                            Some(s) => {
-                                let s_val = self.eval_expr(*s)?;
-                                let s_int = s_val.as_int()?;
-                                if s_int < 0 { len + s_int } else { s_int }
-                            }
-                            None => 0,
-                        };
-                        
-                        // Evaluate end index
-                        let end_idx = match end {
-                            Some(e) => {
-                                let e_val = self.eval_expr(*e)?;
-                                let e_int = e_val.as_int()?;
-                                if e_int < 0 { len + e_int } else { e_int }
-                            }
-                            None => len,
-                        };
-                        
-                        // Clamp indices
-                        let start_idx = start_idx.max(0).min(len) as usize;
-                        let end_idx = end_idx.max(0).min(len) as usize;
-                        
-                        // Extract slice
-                        let sliced: Vec<Value> = guard[start_idx..end_idx].to_vec();
-                        Ok(Value::new_list(sliced))
+                        let indices = slice_indices(guard.len(), start, end, step)?;
+                        Ok(Value::new_list(indices.map(|i| guard[i].clone()).collect()))
+                    }
+                    Value::Bytes(data) => {
+                        let indices = slice_indices(data.len(), start, end, step)?;
+                        Ok(Value::new_bytes(indices.map(|i| data[i]).collect()))
+                    }
+                    Value::Str(s) => {
+                        let chars: Vec<char> = s.chars().collect();
+                        let indices = slice_indices(chars.len(), start, end, step)?;
+                        Ok(Value::Str(indices.map(|i| chars[i]).collect()))
                     }
                     _ => Err(LatchError::TypeMismatch {
-                        expected: "list".into(),
+                        expected: "list, string or bytes".into(),
                         found: list_val.type_name().into(),
                     }),
                 }
             }
+
+            Expr::Spawn(func) => {
+                let func_val = self.eval_expr(*func)?;
+                let (params, body, captured_env) = match func_val {
+                    Value::Fn { params, body, captured_env } => (params, body, captured_env),
+                    other => return Err(LatchError::TypeMismatch {
+                        expected: "fn".into(),
+                        found: other.type_name().into(),
+                    }),
+                };
+
+                let task = Arc::new(TaskState::new());
+                let mut worker = self.fork(self.env.clone());
+                worker.cancel = Some(task.cancel_flag());
+                let handle = task.clone();
+                let signals_seen = runtime::signal::seen();
+                std::thread::spawn(move || {
+                    runtime::signal::set_seen(signals_seen);
+                    let result = worker.call_closure(&params, &body, vec![], captured_env.map(|e| *e));
+                    // Cancellation unwinds the task itself; to whoever awaits
+                    // it, it's an ordinary (catchable) error.
+                    handle.finish(result.map_err(|e| match e {
+                        LatchError::TaskCancelled => LatchError::GenericError("task was cancelled".into()),
+                        other => other,
+                    }));
+                });
+                Ok(Value::Task(task))
+            }
         }
     }
 
@@ -754,11 +1058,14 @@ impl Interpreter {
             }
         }
 
-        // String concatenation
+        // String / bytes concatenation
         if matches!(op, BinOp::Add) {
             if let (Value::Str(a), Value::Str(b)) = (&l, &r) {
                 return Ok(Value::Str(format!("{a}{b}")));
             }
+            if let (Value::Bytes(a), Value::Bytes(b)) = (&l, &r) {
+                return Ok(Value::new_bytes([a.as_slice(), b.as_slice()].concat()));
+            }
         }
 
         // `in` operator: value in container
@@ -774,12 +1081,15 @@ impl Interpreter {
                     Ok(Value::Bool(haystack.contains(needle)))
                 }
                 Value::Map(map) => {
-                    let guard = map.lock().unwrap();
-                    let key = l.as_str()?;
-                    Ok(Value::Bool(guard.contains_key(key)))
+                    let key = HashKey::from_value(&l)?;
+                    Ok(Value::Bool(map.lock().unwrap().contains_key(&key)))
+                }
+                Value::Set(set) => {
+                    let key = HashKey::from_value(&l)?;
+                    Ok(Value::Bool(set.lock().unwrap().contains(&key)))
                 }
                 _ => Err(LatchError::TypeMismatch {
-                    expected: "list, string, or dict".into(),
+                    expected: "list, string, dict, or set".into(),
                     found: r.type_name().into(),
                 }),
             };
@@ -814,6 +1124,16 @@ impl Interpreter {
                 }),
             },
 
+            // Equality for bytes
+            (Value::Bytes(a), Value::Bytes(b)) => match op {
+                BinOp::Eq    => Ok(Value::Bool(a == b)),
+                BinOp::NotEq => Ok(Value::Bool(a != b)),
+                _ => Err(LatchError::TypeMismatch {
+                    expected: "numeric".into(),
+                    found: "bytes".into(),
+                }),
+            },
+
             // Equality for lists
             (Value::List(_), Value::List(_)) => match op {
                 BinOp::Eq    => Ok(Value::Bool(values_equal(&l, &r))),
@@ -843,6 +1163,20 @@ impl Interpreter {
                 })
             },
 
+            // Set algebra and equality
+            (Value::Set(_), Value::Set(_)) => match op {
+                BinOp::BitOr  => runtime::set::combine("union", &l, &r),
+                BinOp::BitAnd => runtime::set::combine("intersection", &l, &r),
+                BinOp::BitXor => runtime::set::combine("symmetric_difference", &l, &r),
+                BinOp::Sub    => runtime::set::combine("difference", &l, &r),
+                BinOp::Eq     => Ok(Value::Bool(values_equal(&l, &r))),
+                BinOp::NotEq  => Ok(Value::Bool(!values_equal(&l, &r))),
+                _ => Err(LatchError::TypeMismatch {
+                    expected: "numeric".into(),
+                    found: "set".into(),
+                }),
+            },
+
             // Equality for dicts
             (Value::Map(_), Value::Map(_)) => match op {
                 BinOp::Eq    => Ok(Value::Bool(values_equal(&l, &r))),
@@ -853,6 +1187,19 @@ impl Interpreter {
                 }),
             },
 
+            // Equality for enums, classes and objects (by identity, unless `__eq__`)
+            (Value::Enum(_) | Value::EnumType(_) | Value::Class(_) | Value::Interface(_) | Value::Instance(_), _)
+            | (_, Value::Enum(_) | Value::EnumType(_) | Value::Class(_) | Value::Interface(_) | Value::Instance(_))
+                if matches!(op, BinOp::Eq | BinOp::NotEq) =>
+            {
+                Ok(Value::Bool(values_equal(&l, &r) == matches!(op, BinOp::Eq)))
+            }
+
+            // Date and duration arithmetic and comparisons
+            (Value::DateTime(_) | Value::Duration(_), _) | (_, Value::DateTime(_) | Value::Duration(_)) => {
+                runtime::time::binop(op, &l, &r)
+            }
+
             _ => Err(LatchError::TypeMismatch {
                 expected: "compatible types".into(),
                 found: format!("{} and {}", l.type_name(), r.type_name()),
@@ -879,6 +1226,9 @@ impl Interpreter {
             BinOp::Gt    => Ok(Value::Bool(a > b)),
             BinOp::LtEq  => Ok(Value::Bool(a <= b)),
             BinOp::GtEq  => Ok(Value::Bool(a >= b)),
+            BinOp::BitOr  => Ok(Value::Int(a | b)),
+            BinOp::BitAnd => Ok(Value::Int(a & b)),
+            BinOp::BitXor => Ok(Value::Int(a ^ b)),
             BinOp::And | BinOp::Or | BinOp::In => Err(LatchError::TypeMismatch {
                 expected: "bool".into(), found: "int".into(),
             }),
@@ -907,34 +1257,108 @@ impl Interpreter {
             BinOp::And | BinOp::Or | BinOp::In => Err(LatchError::TypeMismatch {
                 expected: "bool".into(), found: "float".into(),
             }),
+            BinOp::BitOr | BinOp::BitAnd | BinOp::BitXor => Err(LatchError::TypeMismatch {
+                expected: "int or set".into(), found: "float".into(),
+            }),
         }
     }
 
     // ── Function calls ───────────────────────────────────────
 
-    fn call_function(&mut self, name: &str, args: Vec<Value>) -> Result<Value> {
+    /// Evaluate call arguments, expanding `...list` into positional
+    /// arguments and `...dict` into keyword arguments.
+    fn eval_args(&mut self, args: Vec<Expr>, kwargs: KwArgs) -> Result<(Vec<Value>, KwValues)> {
+        let mut positional = Vec::new();
+        let mut named = Vec::new();
+        for arg in args {
+            match arg {
+                Expr::Spread(inner) => match self.eval_expr(*inner)? {
+                    Value::Map(map) => {
+                        for (k, v) in map.lock().unwrap().iter() {
+                            let HashKey::Str(name) = k else {
+                                return Err(LatchError::TypeError(format!("keyword argument names must be strings, found {k}")));
+                            };
+                            named.push((name.clone(), v.clone()));
+                        }
+                    }
+                    other => positional.extend(other.into_list()?),
+                },
+                other => positional.push(self.eval_expr(other)?),
+            }
+        }
+        for (name, expr) in kwargs {
+            named.push((name, self.eval_expr(expr)?));
+        }
+        Ok((positional, named))
+    }
+
+    fn call_function(&mut self, name: &str, mut args: Vec<Value>, mut kwargs: KwValues) -> Result<Value> {
+        // Builtins receive keyword arguments as a trailing options dict;
+        // user functions bind them by name.
+        if !kwargs.is_empty() && !matches!(self.env.get(name), Some(Value::Fn { .. } | Value::Class(_))) {
+            args.push(kwargs_dict(std::mem::take(&mut kwargs)));
+        }
+
         // Built-in functions
         match name {
             "print" => {
                 if let Some(val) = args.first() {
-                    println!("{val}");
+                    let text = self.display(val)?;
+                    self.out.println(&text);
                 }
                 return Ok(Value::Null);
             }
             "len" => {
+                if let Some(obj @ Value::Instance(_)) = args.first() {
+                    if let Some(n) = self.call_hook(obj, "__len__", Vec::new())? {
+                        return Ok(n);
+                    }
+                }
                 return match args.first() {
                     Some(Value::List(l)) => Ok(Value::Int(l.lock().unwrap().len() as i64)),
-                    Some(Value::Str(s))  => Ok(Value::Int(s.len() as i64)),
+                    Some(Value::Str(s))  => Ok(Value::Int(runtime::text::graphemes(s).len() as i64)),
                     Some(Value::Map(m))  => Ok(Value::Int(m.lock().unwrap().len() as i64)),
+                    Some(Value::Set(s))  => Ok(Value::Int(s.lock().unwrap().len() as i64)),
+                    Some(Value::Bytes(b)) => Ok(Value::Int(b.len() as i64)),
                     _ => Err(LatchError::TypeMismatch {
-                        expected: "list, string, or dict".into(),
+                        expected: "list, string, dict, set, or bytes".into(),
                         found: args.first().map(|v| v.type_name()).unwrap_or("none").into(),
                     }),
                 };
             }
+            // bytes(str, encoding?) / bytes([ints]) — build a byte string
+            "bytes" => {
+                let val = args.first().ok_or_else(|| LatchError::ArgCountMismatch {
+                    name: "bytes".into(), expected: 1, found: 0,
+                })?;
+                return runtime::bytes::from_value(val, args.get(1));
+            }
+            // ok(value) / err(error) — build a result value
+            "ok" | "err" => {
+                let val = Box::new(args.into_iter().next().unwrap_or(Value::Null));
+                return Ok(Value::Result(if name == "ok" { Ok(val) } else { Err(val) }));
+            }
+            // unwrap(result) — the value of an ok result; raises the error of an err one
+            "unwrap" => {
+                return match args.into_iter().next() {
+                    Some(Value::Result(Ok(val))) => Ok(*val),
+                    Some(Value::Result(Err(e))) => Err(LatchError::GenericError(e.to_string())),
+                    other => Err(LatchError::TypeMismatch {
+                        expected: "result".into(),
+                        found: other.map(|v| v.type_name()).unwrap_or("none").into(),
+                    }),
+                };
+            }
+            // set() / set(list) — build a set
+            "set" => {
+                return match args.first() {
+                    Some(v) => runtime::set::from_value(v),
+                    None => Ok(Value::new_set([])),
+                };
+            }
             "str" => {
                 return match args.first() {
-                    Some(val) => Ok(Value::Str(format!("{val}"))),
+                    Some(val) => Ok(Value::Str(self.display(val)?)),
                     None => Ok(Value::Str(String::new())),
                 };
             }
@@ -970,10 +1394,49 @@ impl Interpreter {
             }
             "typeof" => {
                 return match args.first() {
+                    // An enum value's type is its enum: typeof(Env.Dev) == "Env"
+                    Some(Value::Enum(e)) => Ok(Value::Str(e.def.name.clone())),
+                    // ...and an object's is its class: typeof(Service("api")) == "Service"
+                    Some(Value::Instance(obj)) => Ok(Value::Str(obj.class.name.clone())),
                     Some(val) => Ok(Value::Str(val.type_name().to_string())),
                     None => Ok(Value::Str("none".into())),
                 };
             }
+            // is_a(value, Class | Interface) — is value an object of that class
+            // (or a subclass), or of a class implementing that interface?
+            "is_a" => {
+                return match (args.first(), args.get(1)) {
+                    (Some(Value::Instance(obj)), Some(Value::Class(class))) => Ok(Value::Bool(obj.class.is_subclass_of(class))),
+                    (Some(Value::Instance(obj)), Some(Value::Interface(iface))) => Ok(Value::Bool(obj.class.implements(iface))),
+                    (Some(_), Some(Value::Class(_) | Value::Interface(_))) => Ok(Value::Bool(false)),
+                    (_, other) => Err(LatchError::TypeMismatch {
+                        expected: "class or interface".into(),
+                        found: other.map(|v| v.type_name()).unwrap_or("none").into(),
+                    }),
+                };
+            }
+            // on_signal("SIGINT", fn(sig) { ... }) — run fn instead of stopping
+            // the script; a null handler removes the trap
+            "on_signal" => {
+                let sig = runtime::signal::number("on_signal", args.first(), 0)?;
+                let sig = runtime::signal::trappable("on_signal", sig)?;
+                let mut traps = self.traps.lock().unwrap();
+                match args.get(1) {
+                    Some(handler @ Value::Fn { .. }) => {
+                        traps.insert(sig, handler.clone());
+                        runtime::signal::set_trapped(sig, true);
+                    }
+                    Some(Value::Null) => {
+                        traps.remove(&sig);
+                        runtime::signal::set_trapped(sig, false);
+                    }
+                    other => return Err(LatchError::TypeMismatch {
+                        expected: "fn or null for on_signal".into(),
+                        found: other.map(|v| v.type_name()).unwrap_or("none").into(),
+                    }),
+                }
+                return Ok(Value::Null);
+            }
             "push" => {
                 if args.len() == 2 {
                     if let Value::List(ref list) = args[0] {
@@ -1030,10 +1493,9 @@ impl Interpreter {
             "remove" => {
                 if args.len() == 2 {
                     if let Value::List(ref list) = args[0] {
-                        let mut guard = list.lock().unwrap();
-                        let val = &args[1];
-                        if let Some(pos) = guard.iter().position(|x| values_equal(x, val)) {
-                            guard.remove(pos);
+                        let items = list.lock().unwrap().clone();
+                        if let Some(pos) = self.position(&items, &args[1])? {
+                            list.lock().unwrap().remove(pos);
                             return Ok(Value::Null);
                         }
                         return Err(LatchError::GenericError("value not found in list".into()));
@@ -1049,7 +1511,7 @@ impl Interpreter {
             // pop(dict, key, default?) - remove and return value from dict
             "pop" => {
                 // Try list pop first (1 or 2 args)
-                if args.len() >= 1 {
+                if !args.is_empty() {
                     if let Value::List(ref list) = args[0] {
                         let mut guard = list.lock().unwrap();
                         if guard.is_empty() {
@@ -1075,8 +1537,8 @@ impl Interpreter {
                     if let Value::Map(ref m) = args[0] {
                         if args.len() >= 2 {
                             let mut guard = m.lock().unwrap();
-                            let key = args[1].as_str()?;
-                            if let Some(val) = guard.remove(key) {
+                            let key = HashKey::from_value(&args[1])?;
+                            if let Some(val) = guard.shift_remove(&key) {
                                 return Ok(val);
                             }
                             if args.len() >= 3 {
@@ -1110,9 +1572,8 @@ impl Interpreter {
             "index" => {
                 if args.len() == 2 {
                     if let Value::List(ref list) = args[0] {
-                        let guard = list.lock().unwrap();
-                        let val = &args[1];
-                        if let Some(pos) = guard.iter().position(|x| values_equal(x, val)) {
+                        let items = list.lock().unwrap().clone();
+                        if let Some(pos) = self.position(&items, &args[1])? {
                             return Ok(Value::Int(pos as i64));
                         }
                         return Err(LatchError::GenericError("value not found in list".into()));
@@ -1128,9 +1589,13 @@ impl Interpreter {
             "count" => {
                 if args.len() == 2 {
                     if let Value::List(ref list) = args[0] {
-                        let guard = list.lock().unwrap();
-                        let val = &args[1];
-                        let cnt = guard.iter().filter(|x| values_equal(x, val)).count();
+                        let items = list.lock().unwrap().clone();
+                        let mut cnt = 0;
+                        for item in &items {
+                            if self.equals(item, &args[1])? {
+                                cnt += 1;
+                            }
+                        }
                         return Ok(Value::Int(cnt as i64));
                     }
                 }
@@ -1171,9 +1636,7 @@ impl Interpreter {
                 return match args.first() {
                     Some(Value::Map(m)) => {
                         let guard = m.lock().unwrap();
-                        let mut keys: Vec<String> = guard.keys().cloned().collect();
-                        keys.sort();
-                        let keys: Vec<Value> = keys.into_iter().map(Value::Str).collect();
+                        let keys: Vec<Value> = guard.keys().map(HashKey::to_value).collect();
                         Ok(Value::new_list(keys))
                     }
                     _ => Err(LatchError::TypeMismatch {
@@ -1190,8 +1653,7 @@ impl Interpreter {
                 if args.len() >= 2 {
                     if let Value::Map(ref m) = args[0] {
                         let guard = m.lock().unwrap();
-                        let key = args[1].as_str()?;
-                        if let Some(val) = guard.get(key) {
+                        if let Some(val) = guard.get(&HashKey::from_value(&args[1])?) {
                             return Ok(val.clone());
                         }
                         // Return default if provided, otherwise null
@@ -1212,10 +1674,9 @@ impl Interpreter {
                 if args.len() == 1 {
                     if let Value::Map(ref m) = args[0] {
                         let mut guard = m.lock().unwrap();
-                        if let Some(key) = guard.keys().next().cloned() {
-                            if let Some(val) = guard.remove(&key) {
-                                return Ok(Value::new_list(vec![Value::Str(key), val]));
-                            }
+                        // Most recently inserted entry first
+                        if let Some((key, val)) = guard.pop() {
+                            return Ok(Value::new_list(vec![key.to_value(), val]));
                         }
                         return Err(LatchError::GenericError("popitem from empty dict".into()));
                     }
@@ -1249,11 +1710,11 @@ impl Interpreter {
                 if args.len() == 3 {
                     if let Value::Map(ref m) = args[0] {
                         let mut guard = m.lock().unwrap();
-                        let key = args[1].as_str()?;
-                        if let Some(val) = guard.get(key) {
+                        let key = HashKey::from_value(&args[1])?;
+                        if let Some(val) = guard.get(&key) {
                             return Ok(val.clone());
                         }
-                        guard.insert(key.to_string(), args[2].clone());
+                        guard.insert(key, args[2].clone());
                         return Ok(args[2].clone());
                     }
                 }
@@ -1282,7 +1743,7 @@ impl Interpreter {
                 if args.len() == 1 {
                     if let Value::Map(ref m) = args[0] {
                         let guard = m.lock().unwrap();
-                        let copy: HashMap<String, Value> = guard.clone();
+                        let copy: Dict = guard.clone();
                         return Ok(Value::Map(Arc::new(Mutex::new(copy))));
                     }
                 }
@@ -1297,14 +1758,9 @@ impl Interpreter {
                 if args.len() == 1 {
                     if let Value::Map(ref m) = args[0] {
                         let guard = m.lock().unwrap();
-                        let mut items: Vec<Value> = Vec::new();
-                        let mut sorted_keys: Vec<String> = guard.keys().cloned().collect();
-                        sorted_keys.sort();
-                        for key in sorted_keys {
-                            if let Some(val) = guard.get(&key) {
-                                items.push(Value::new_list(vec![Value::Str(key), val.clone()]));
-                            }
-                        }
+                        let items: Vec<Value> = guard.iter()
+                            .map(|(k, v)| Value::new_list(vec![k.to_value(), v.clone()]))
+                            .collect();
                         return Ok(Value::new_list(items));
                     }
                 }
@@ -1320,10 +1776,9 @@ impl Interpreter {
                     if let Value::List(ref keys) = args[0] {
                         let guard = keys.lock().unwrap();
                         let value = args[1].clone();
-                        let mut map = HashMap::new();
+                        let mut map = Dict::new();
                         for key in guard.iter() {
-                            let k = key.as_str()?;
-                            map.insert(k.to_string(), value.clone());
+                            map.insert(HashKey::from_value(key)?, value.clone());
                         }
                         return Ok(Value::Map(Arc::new(Mutex::new(map))));
                     }
@@ -1337,9 +1792,7 @@ impl Interpreter {
                 return match args.first() {
                     Some(Value::Map(m)) => {
                         let guard = m.lock().unwrap();
-                        let mut sorted_keys: Vec<String> = guard.keys().cloned().collect();
-                        sorted_keys.sort();
-                        let vals: Vec<Value> = sorted_keys.iter().map(|k| guard[k].clone()).collect();
+                        let vals: Vec<Value> = guard.values().cloned().collect();
                         Ok(Value::new_list(vals))
                     }
                     _ => Err(LatchError::TypeMismatch {
@@ -1426,9 +1879,8 @@ impl Interpreter {
                             Ok(Value::Bool(haystack.contains(needle.as_str())))
                         }
                         (Value::List(list), val) => {
-                            let guard = list.lock().unwrap();
-                            let found = guard.iter().any(|item| values_equal(item, val));
-                            Ok(Value::Bool(found))
+                            let items = list.lock().unwrap().clone();
+                            Ok(Value::Bool(self.position(&items, val)?.is_some()))
                         }
                         _ => Err(LatchError::TypeMismatch {
                             expected: "string or list".into(),
@@ -1458,7 +1910,8 @@ impl Interpreter {
                 if args.len() == 2 {
                     let s = args[0].as_str()?;
                     let sub = args[1].as_str()?;
-                    return Ok(Value::Int(s.find(sub).map(|i| i as i64).unwrap_or(-1)));
+                    // Char index, so the result can be used to index or slice `s`
+                    return Ok(Value::Int(s.find(sub).map(|i| s[..i].chars().count() as i64).unwrap_or(-1)));
                 }
                 return Err(LatchError::ArgCountMismatch {
                     name: "str_find".into(), expected: 2, found: args.len(),
@@ -1470,7 +1923,7 @@ impl Interpreter {
                 if args.len() == 2 {
                     let s = args[0].as_str()?;
                     let sub = args[1].as_str()?;
-                    return Ok(Value::Int(s.rfind(sub).map(|i| i as i64).unwrap_or(-1)));
+                    return Ok(Value::Int(s.rfind(sub).map(|i| s[..i].chars().count() as i64).unwrap_or(-1)));
                 }
                 return Err(LatchError::ArgCountMismatch {
                     name: "str_rfind".into(), expected: 2, found: args.len(),
@@ -1560,6 +2013,47 @@ impl Interpreter {
                     name: "str_capitalize".into(), expected: 1, found: args.len(),
                 });
             }
+            // chars(s) — Unicode scalars, the units of s[i] and s[a:b]
+            "chars" => {
+                let s = str_arg(name, &args, 1)?;
+                return Ok(Value::new_list(s.chars().map(|c| Value::Str(c.to_string())).collect()));
+            }
+            // graphemes(s) — user-perceived characters, the units of len(s)
+            "graphemes" => {
+                let s = str_arg(name, &args, 1)?;
+                let items = runtime::text::graphemes(s).into_iter().map(|g| Value::Str(g.to_string())).collect();
+                return Ok(Value::new_list(items));
+            }
+            // str_width(s) — display width in terminal columns
+            "str_width" => {
+                let s = str_arg(name, &args, 1)?;
+                return Ok(Value::Int(runtime::text::width(s) as i64));
+            }
+            // pad_left(s, width, fill=" ") / pad_right / center — pad to a display width
+            "pad_left" | "pad_right" | "center" => {
+                let s = str_arg(name, &args, 2)?;
+                let cols = args[1].as_int()?.max(0) as usize;
+                let fill = match args.get(2) {
+                    Some(f) => f.as_str()?,
+                    None => " ",
+                };
+                let align = match name {
+                    "pad_left" => runtime::text::Align::Right,
+                    "pad_right" => runtime::text::Align::Left,
+                    _ => runtime::text::Align::Center,
+                };
+                return Ok(Value::Str(runtime::text::pad(s, cols, fill, align)?));
+            }
+            // truncate(s, width, ellipsis="…") — cut to a display width
+            "truncate" => {
+                let s = str_arg(name, &args, 2)?;
+                let cols = args[1].as_int()?.max(0) as usize;
+                let ellipsis = match args.get(2) {
+                    Some(e) => e.as_str()?,
+                    None => "…",
+                };
+                return Ok(Value::Str(runtime::text::truncate(s, cols, ellipsis)));
+            }
             "repeat" => {
                 if args.len() == 2 {
                     let s = args[0].as_str()?.to_string();
@@ -1576,7 +2070,7 @@ impl Interpreter {
 
             // str_strip(string, chars?) - strip whitespace or specified chars
             "str_strip" => {
-                if args.len() >= 1 {
+                if !args.is_empty() {
                     let s = args[0].as_str()?;
                     let result = if args.len() >= 2 {
                         let chars = args[1].as_str()?;
@@ -1593,7 +2087,7 @@ impl Interpreter {
 
             // str_lstrip(string, chars?) - strip from left
             "str_lstrip" => {
-                if args.len() >= 1 {
+                if !args.is_empty() {
                     let s = args[0].as_str()?;
                     let result = if args.len() >= 2 {
                         let chars = args[1].as_str()?;
@@ -1610,7 +2104,7 @@ impl Interpreter {
 
             // str_rstrip(string, chars?) - strip from right
             "str_rstrip" => {
-                if args.len() >= 1 {
+                if !args.is_empty() {
                     let s = args[0].as_str()?;
                     let result = if args.len() >= 2 {
                         let chars = args[1].as_str()?;
@@ -1686,7 +2180,7 @@ impl Interpreter {
 
             // assert(condition, message) — throws error if condition is falsy
             "assert" => {
-                if args.len() >= 1 {
+                if !args.is_empty() {
                     let condition = args[0].is_truthy();
                     if !condition {
                         let message = if args.len() >= 2 {
@@ -1823,25 +2317,60 @@ impl Interpreter {
                 });
             }
 
-            "sort" => {
-                return match args.into_iter().next() {
-                    Some(Value::List(list)) => {
-                        let mut vec = list.lock().unwrap().clone();
-                        vec.sort_by(|a, b| {
-                            match (a, b) {
-                                (Value::Int(x), Value::Int(y)) => x.cmp(y),
-                                (Value::Float(x), Value::Float(y)) => x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal),
-                                (Value::Str(x), Value::Str(y)) => x.cmp(y),
-                                _ => std::cmp::Ordering::Equal,
-                            }
-                        });
-                        Ok(Value::new_list(vec))
-                    }
-                    _ => Err(LatchError::TypeMismatch {
+            // sort(list, key=?, cmp=?, reverse=?) — stable sort into a new list.
+            // `key` is a fn, a field name, or a list of those for multi-key ordering.
+            "sort" | "sorted_by" => {
+                let list = match args.first() {
+                    Some(v @ (Value::List(_) | Value::Set(_))) => v.as_list()?,
+                    other => return Err(LatchError::TypeMismatch {
                         expected: "list".into(),
-                        found: "invalid args".into(),
+                        found: other.map(|v| v.type_name()).unwrap_or("none").into(),
                     }),
                 };
+                let opts = SortOpts::from_args(&args[1..])?;
+                if name == "sorted_by" && opts.key.is_none() {
+                    return Err(LatchError::ArgCountMismatch { name: name.into(), expected: 2, found: args.len() });
+                }
+                return Ok(Value::new_list(self.sort_values(list, &opts)?));
+            }
+
+            // min_by(list, key) / max_by(list, key) — the first item with the smallest/largest key
+            "min_by" | "max_by" => {
+                let (list, key) = keyed_list_args(name, &args)?;
+                let want = if name == "min_by" { cmp::Ordering::Less } else { cmp::Ordering::Greater };
+                let mut best: Option<(Value, Value)> = None;
+                for item in list {
+                    let k = self.sort_key(&key, &item)?;
+                    if best.as_ref().is_none_or(|(bk, _)| compare_values(&k, bk) == want) {
+                        best = Some((k, item));
+                    }
+                }
+                return best.map(|(_, item)| item)
+                    .ok_or_else(|| LatchError::GenericError(format!("{name}() called on empty list")));
+            }
+
+            // group_by(list, key) — dict of key → items, in first-seen order
+            "group_by" => {
+                let (list, key) = keyed_list_args(name, &args)?;
+                let mut groups: IndexMap<HashKey, Vec<Value>> = IndexMap::new();
+                for item in list {
+                    let k = HashKey::from_value(&self.sort_key(&key, &item)?)?;
+                    groups.entry(k).or_default().push(item);
+                }
+                return Ok(Value::new_map(groups.into_iter().map(|(k, v)| (k, Value::new_list(v))).collect()));
+            }
+
+            // unique_by(list, key) — keep the first item for each distinct key
+            "unique_by" => {
+                let (list, key) = keyed_list_args(name, &args)?;
+                let mut seen = IndexSet::new();
+                let mut result = Vec::new();
+                for item in list {
+                    if seen.insert(HashKey::from_value(&self.sort_key(&key, &item)?)?) {
+                        result.push(item);
+                    }
+                }
+                return Ok(Value::new_list(result));
             }
 
             // filter(list, fn) — returns items where fn(item) is truthy
@@ -1910,6 +2439,78 @@ impl Interpreter {
                 });
             }
 
+            // await(task, timeout_ms?) — block until the task finishes, return its value
+            "await" => {
+                let task = task_arg(args.first())?;
+                let timeout = timeout_arg(args.get(1))?;
+                return match task.wait(timeout) {
+                    Some(result) => result,
+                    None => Err(LatchError::GenericError(format!(
+                        "await: task did not finish within {}ms", timeout.unwrap_or_default().as_millis()
+                    ))),
+                };
+            }
+
+            // is_done(task) — true once the task has finished (or failed)
+            "is_done" => {
+                let task = task_arg(args.first())?;
+                return Ok(Value::Bool(task.is_done()));
+            }
+
+            // cancel(task) — ask the task to stop at its next statement
+            "cancel" => {
+                let task = task_arg(args.first())?;
+                return Ok(Value::Bool(task.cancel()));
+            }
+
+            // await_all(tasks, timeout_ms?) — wait for every task, return their values in order
+            "await_all" => {
+                let tasks = args.first().cloned().unwrap_or(Value::Null).into_list()?
+                    .iter()
+                    .map(|t| task_arg(Some(t)))
+                    .collect::<Result<Vec<_>>>()?;
+                let deadline = timeout_arg(args.get(1))?.map(|t| Instant::now() + t);
+
+                // Like `parallel`: let every task finish, then report the first error.
+                let mut results = Vec::new();
+                for task in &tasks {
+                    let remaining = deadline.map(|d| d.saturating_duration_since(Instant::now()));
+                    match task.wait(remaining) {
+                        Some(result) => results.push(result),
+                        None => return Err(LatchError::GenericError(
+                            "await_all: tasks did not finish before the timeout".into(),
+                        )),
+                    }
+                }
+                let values = results.into_iter().collect::<Result<Vec<_>>>()?;
+                return Ok(Value::new_list(values));
+            }
+
+            // await_any(tasks, timeout_ms?) — return the value of whichever task finishes first
+            "await_any" => {
+                let tasks = args.first().cloned().unwrap_or(Value::Null).into_list()?
+                    .iter()
+                    .map(|t| task_arg(Some(t)))
+                    .collect::<Result<Vec<_>>>()?;
+                if tasks.is_empty() {
+                    return Err(LatchError::GenericError("await_any: empty task list".into()));
+                }
+                let deadline = timeout_arg(args.get(1))?.map(|t| Instant::now() + t);
+                loop {
+                    for task in &tasks {
+                        if let Some(result) = task.wait(Some(Duration::ZERO)) {
+                            return result;
+                        }
+                    }
+                    if deadline.is_some_and(|d| Instant::now() >= d) {
+                        return Err(LatchError::GenericError(
+                            "await_any: no task finished before the timeout".into(),
+                        ));
+                    }
+                    std::thread::sleep(Duration::from_millis(5));
+                }
+            }
+
             _ => {}
         }
 
@@ -1917,8 +2518,9 @@ impl Interpreter {
         let func = self.env.get(name).cloned();
         match func {
             Some(Value::Fn { params, body, captured_env }) => {
-                self.call_closure(&params, &body, args, captured_env.map(|e| *e))
+                self.call_closure_kw(&params, &body, args, kwargs, captured_env.map(|e| *e))
             }
+            Some(Value::Class(class)) => self.instantiate(&class, args, kwargs),
             _ => Err(LatchError::UndefinedFunction(name.to_string())),
         }
     }
@@ -1927,6 +2529,19 @@ impl Interpreter {
     /// If `captured_env` is provided, use it as the parent scope (closure semantics).
     /// Otherwise, use the current env as the parent (regular function call).
     fn call_closure(&mut self, params: &[Param], body: &Block, args: Vec<Value>, captured_env: Option<Env>) -> Result<Value> {
+        self.call_closure_kw(params, body, args, Vec::new(), captured_env)
+    }
+
+    /// `call_closure` with keyword arguments, which bind to parameters by name
+    /// and otherwise land in a `**opts` parameter.
+    fn call_closure_kw(
+        &mut self,
+        params: &[Param],
+        body: &Block,
+        args: Vec<Value>,
+        mut kwargs: KwValues,
+        captured_env: Option<Env>,
+    ) -> Result<Value> {
         // Save the caller's environment
         let caller_env = std::mem::replace(&mut self.env, Env::new());
 
@@ -1936,26 +2551,15 @@ impl Interpreter {
             None => caller_env.clone().child(),   // regular fn: parent = caller env
         };
 
-        // Bind parameters to arguments (with default values if needed)
-        for (i, param) in params.iter().enumerate() {
-            if i < args.len() {
-                // Use provided argument
-                self.env.set(&param.name, args[i].clone());
-            } else if let Some(ref default_expr) = param.default {
-                // Use default value
-                let default_val = self.eval_expr(default_expr.clone())?;
-                self.env.set(&param.name, default_val);
-            } else {
-                // Missing argument without default
-                return Err(LatchError::ArgCountMismatch {
-                    name: param.name.clone(),
-                    expected: params.len(),
-                    found: args.len(),
-                });
-            }
+        let result = self.bind_params(params, args, &mut kwargs);
+        if let Err(e) = result {
+            self.env = caller_env;
+            return Err(e);
         }
 
+        self.defers.push(Vec::new());
         let result = self.exec_block_inner(body.clone());
+        let result = self.run_deferred(result);
 
         // Restore the caller's environment
         self.env = caller_env;
@@ -1966,9 +2570,761 @@ impl Interpreter {
             Err(e) => Err(e),
         }
     }
+
+    /// `call_module`, except that `proc.exec`/`proc.pipe` with `stream=true`
+    /// or an `on_line` callback report output lines while the command runs:
+    /// echoed through the script's output (after `prefix`, stderr lines to
+    /// stderr) and/or passed to `on_line(stream, line)`.
+    fn call_module(&mut self, module: &str, method: &str, mut args: Vec<Value>) -> Result<Value> {
+        // Strict mode: commands fail on a non-zero exit unless the call says check=false
+        if self.strict && module == "proc" && matches!(method, "exec" | "pipe") && !args.is_empty() {
+            let mut opts = match args.get(1) {
+                Some(Value::Map(m)) => m.lock().unwrap().clone(),
+                _ => Dict::new(),
+            };
+            opts.entry(HashKey::from("check")).or_insert(Value::Bool(true));
+            args.truncate(1);
+            args.push(Value::new_map(opts));
+        }
+        let opts = match (module, method, args.get(1)) {
+            ("proc", "exec" | "pipe", Some(Value::Map(opts))) => opts.lock().unwrap().clone(),
+            _ => return call_module(module, method, args),
+        };
+        let stream = opts.get(&HashKey::from("stream")).is_some_and(Value::is_truthy);
+        let prefix = match opts.get(&HashKey::from("prefix")) {
+            Some(Value::Null) | None => String::new(),
+            Some(p) => p.as_str()?.to_string(),
+        };
+        let on_line = match opts.get(&HashKey::from("on_line")) {
+            Some(Value::Fn { params, body, captured_env }) => Some((params.clone(), body.clone(), captured_env.clone())),
+            Some(Value::Null) | None => None,
+            Some(other) => return Err(LatchError::TypeMismatch {
+                expected: format!("fn(stream, line) for proc.{method} on_line"),
+                found: other.type_name().into(),
+            }),
+        };
+        if !stream && on_line.is_none() {
+            return call_module(module, method, args);
+        }
+
+        let out = self.out.clone();
+        let mut sink = |s: runtime::proc::Stream, line: &str| {
+            if stream {
+                let text = format!("{prefix}{line}");
+                match s {
+                    runtime::proc::Stream::Stdout => out.println(&text),
+                    runtime::proc::Stream::Stderr => out.eprintln(&text),
+                }
+            }
+            if let Some((params, body, captured_env)) = &on_line {
+                let args = vec![Value::Str(s.name().to_string()), Value::Str(line.to_string())];
+                self.call_closure(params, body, args, captured_env.as_deref().cloned())?;
+            }
+            Ok(())
+        };
+        runtime::proc::call_with_lines(method, args, Some(&mut sink))
+    }
+
+    /// Act on a pending signal: run its `on_signal` trap, or unwind the
+    /// script with an Interrupted error (`finally` blocks and defers still run).
+    /// Workers only stop; traps run on the main thread.
+    fn poll_signal(&mut self) -> Result<()> {
+        if self.worker {
+            return runtime::signal::check();
+        }
+        let Some(sig) = runtime::signal::take_unseen() else { return Ok(()) };
+        let trap = self.traps.lock().unwrap().get(&sig).cloned();
+        match trap {
+            Some(Value::Fn { params, body, captured_env }) => {
+                let args = if params.is_empty() { Vec::new() } else { vec![Value::Str(runtime::signal::name(sig))] };
+                self.call_closure(&params, &body, args, captured_env.map(|e| *e))?;
+                Ok(())
+            }
+            _ => Err(LatchError::Interrupted(sig)),
+        }
+    }
+
+    /// Build an object: field defaults from the root class down, then `init`
+    /// with the call's arguments. Without an `init`, keyword arguments set
+    /// declared fields: `Point(x=1, y=2)`.
+    fn instantiate(&mut self, class: &Arc<ClassDef>, args: Vec<Value>, kwargs: KwValues) -> Result<Value> {
+        let mut fields = IndexMap::new();
+        let chain: Vec<&ClassDef> = class.ancestry().collect();
+        for c in chain.into_iter().rev() {
+            for (field, default) in &c.fields {
+                let val = match default {
+                    Some(expr) => self.eval_expr(expr.clone())?,
+                    None => Value::Null,
+                };
+                fields.insert(field.clone(), val);
+            }
+        }
+        let obj = Arc::new(Instance { class: class.clone(), fields: Mutex::new(fields) });
+
+        if let Some((owner, init)) = class.find_method("init") {
+            self.invoke_method(&obj, owner, init, args, kwargs)?;
+        } else {
+            if !args.is_empty() {
+                return Err(LatchError::ArgCountMismatch { name: class.name.clone(), expected: 0, found: args.len() });
+            }
+            let mut fields = obj.fields.lock().unwrap();
+            for (field, val) in kwargs {
+                if !fields.contains_key(&field) {
+                    return Err(LatchError::KeyNotFound(format!("{}.{field}", class.name)));
+                }
+                fields.insert(field, val);
+            }
+        }
+        Ok(Value::Instance(obj))
+    }
+
+    /// Call `obj.method(...)`, looking the method up from `from`: the object's
+    /// own class, or the parent of the running method's class for `super`.
+    fn call_method(
+        &mut self,
+        obj: &Arc<Instance>,
+        from: &Arc<ClassDef>,
+        method: &str,
+        args: Vec<Value>,
+        kwargs: KwValues,
+    ) -> Result<Value> {
+        let (owner, m) = from.find_method(method)
+            .ok_or_else(|| LatchError::KeyNotFound(format!("{}.{method}", obj.class.name)))?;
+        self.invoke_method(obj, owner, m, args, kwargs)
+    }
+
+    /// Run a method body like a named function, with `self` bound to `obj`.
+    fn invoke_method(
+        &mut self,
+        obj: &Arc<Instance>,
+        owner: Arc<ClassDef>,
+        method: Method,
+        args: Vec<Value>,
+        kwargs: KwValues,
+    ) -> Result<Value> {
+        let mut scope = self.env.clone().child();
+        scope.set("self", Value::Instance(obj.clone()));
+        scope.set(SUPER_CLASS, Value::Class(owner));
+        self.call_closure_kw(&method.params, &method.body, args, kwargs, Some(scope))
+    }
+
+    /// Call a dunder hook (`__str__`, `__len__`, ...) if `val` is an object
+    /// whose class defines it.
+    fn call_hook(&mut self, val: &Value, hook: &str, args: Vec<Value>) -> Result<Option<Value>> {
+        let Value::Instance(obj) = val else { return Ok(None) };
+        match obj.class.find_method(hook) {
+            Some((owner, m)) => self.invoke_method(obj, owner, m, args, Vec::new()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Text for `print`, `str()` and interpolation: an object's `__str__`
+    /// when it has one, including objects inside lists, dicts and the like.
+    fn display(&mut self, val: &Value) -> Result<String> {
+        if let Some(text) = self.call_hook(val, "__str__", Vec::new())? {
+            return Ok(text.to_string());
+        }
+        // Mirrors `Display for Value`, with each element going through here.
+        // Items are copied out first, so a hook can use the same list.
+        Ok(match val {
+            Value::List(items) => {
+                let items = items.lock().unwrap().clone();
+                format!("[{}]", self.display_all(&items)?)
+            }
+            Value::Map(map) => {
+                let entries = map.lock().unwrap().clone();
+                let parts = entries.iter()
+                    .map(|(k, v)| Ok(format!("{k}: {}", self.display(v)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                format!("{{{}}}", parts.join(", "))
+            }
+            Value::Result(Ok(v)) => format!("ok({})", self.display(v)?),
+            Value::Result(Err(e)) => format!("err({})", self.display(e)?),
+            Value::Enum(e) if !e.payload.is_empty() => {
+                format!("{}.{}({})", e.def.name, e.variant_name(), self.display_all(&e.payload)?)
+            }
+            Value::Instance(obj) => {
+                let fields = obj.fields.lock().unwrap().clone();
+                let parts = fields.iter()
+                    .map(|(k, v)| Ok(format!("{k}={}", self.display(v)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                format!("{}({})", obj.class.name, parts.join(", "))
+            }
+            _ => val.to_string(),
+        })
+    }
+
+    /// `display` of each item, comma-separated.
+    fn display_all(&mut self, items: &[Value]) -> Result<String> {
+        let parts = items.iter().map(|v| self.display(v)).collect::<Result<Vec<_>>>()?;
+        Ok(parts.join(", "))
+    }
+
+    /// `values_equal`, except that an object with `__eq__` decides for itself,
+    /// including inside lists, dicts, results and enum payloads.
+    fn equals(&mut self, a: &Value, b: &Value) -> Result<bool> {
+        if let Some(eq) = self.call_hook(a, "__eq__", vec![b.clone()])? {
+            return Ok(eq.is_truthy());
+        }
+        match (a, b) {
+            (Value::List(x), Value::List(y)) => {
+                let x = x.lock().unwrap().clone();
+                let y = y.lock().unwrap().clone();
+                self.all_equal(&x, &y)
+            }
+            (Value::Map(x), Value::Map(y)) => {
+                let x = x.lock().unwrap().clone();
+                let y = y.lock().unwrap().clone();
+                if x.len() != y.len() {
+                    return Ok(false);
+                }
+                for (k, v) in &x {
+                    match y.get(k) {
+                        Some(yv) if self.equals(v, yv)? => {}
+                        _ => return Ok(false),
+                    }
+                }
+                Ok(true)
+            }
+            (Value::Result(Ok(x)), Value::Result(Ok(y))) | (Value::Result(Err(x)), Value::Result(Err(y))) => {
+                self.equals(x, y)
+            }
+            (Value::Enum(x), Value::Enum(y)) => {
+                Ok(x.def == y.def && x.variant == y.variant && self.all_equal(&x.payload, &y.payload)?)
+            }
+            _ => Ok(values_equal(a, b)),
+        }
+    }
+
+    /// Pairwise `equals` over two sequences of the same length.
+    fn all_equal(&mut self, xs: &[Value], ys: &[Value]) -> Result<bool> {
+        if xs.len() != ys.len() {
+            return Ok(false);
+        }
+        for (x, y) in xs.iter().zip(ys) {
+            if !self.equals(x, y)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Index of the first item `equals` to `val`.
+    fn position(&mut self, items: &[Value], val: &Value) -> Result<Option<usize>> {
+        for (i, item) in items.iter().enumerate() {
+            if self.equals(item, val)? {
+                return Ok(Some(i));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Items for `for`, `parallel` and comprehensions: an object iterates
+    /// over whatever its `__iter__` returns.
+    fn iterate(&mut self, val: Value) -> Result<Vec<Value>> {
+        match self.call_hook(&val, "__iter__", Vec::new())? {
+            Some(items) => items.into_list(),
+            None => val.into_list(),
+        }
+    }
+
+    /// `eval_binop`, except that an object on the left can overload the
+    /// operator: `__add__`, `__sub__`, `__mul__`, `__div__`, `__mod__`,
+    /// `__lt__`, `__le__`, `__gt__`, `__ge__`, and `__eq__` (negated for `!=`).
+    fn binop(&mut self, op: BinOp, l: Value, r: Value) -> Result<Value> {
+        let hook = match op {
+            BinOp::Add => "__add__",
+            BinOp::Sub => "__sub__",
+            BinOp::Mul => "__mul__",
+            BinOp::Div => "__div__",
+            BinOp::Mod => "__mod__",
+            BinOp::Eq | BinOp::NotEq => "__eq__",
+            BinOp::Lt => "__lt__",
+            BinOp::LtEq => "__le__",
+            BinOp::Gt => "__gt__",
+            BinOp::GtEq => "__ge__",
+            // Membership compares with `__eq__` too
+            BinOp::In => {
+                if let Value::List(items) = &r {
+                    let items = items.lock().unwrap().clone();
+                    return Ok(Value::Bool(self.position(&items, &l)?.is_some()));
+                }
+                return self.eval_binop(op, l, r);
+            }
+            _ => return self.eval_binop(op, l, r),
+        };
+        match self.call_hook(&l, hook, vec![r.clone()])? {
+            Some(val) if op == BinOp::NotEq => Ok(Value::Bool(!val.is_truthy())),
+            Some(val) => Ok(val),
+            // Containers whose elements may have `__eq__`
+            None if matches!(op, BinOp::Eq | BinOp::NotEq)
+                && matches!((&l, &r), (Value::List(_), Value::List(_)) | (Value::Map(_), Value::Map(_)) | (Value::Enum(_), Value::Enum(_))) =>
+            {
+                Ok(Value::Bool(self.equals(&l, &r)? == (op == BinOp::Eq)))
+            }
+            None => self.eval_binop(op, l, r),
+        }
+    }
+
+    /// Run comprehension clauses as nested loops, calling `emit` for every
+    /// combination that passes the `if` clauses. Each iteration gets its own scope.
+    fn run_comprehension(
+        &mut self,
+        clauses: &[CompClause],
+        emit: &mut dyn FnMut(&mut Self) -> Result<()>,
+    ) -> Result<()> {
+        let Some((clause, rest)) = clauses.split_first() else {
+            return emit(self);
+        };
+        match clause {
+            CompClause::If(cond) => {
+                if self.eval_expr(cond.clone())?.is_truthy() {
+                    self.run_comprehension(rest, emit)?;
+                }
+                Ok(())
+            }
+            CompClause::For { vars, iter } => {
+                let iterable = self.eval_expr(iter.clone())?;
+                for item in self.iterate(iterable)? {
+                    self.push_scope();
+                    let result = self.bind_loop_vars(vars, item)
+                        .and_then(|()| self.run_comprehension(rest, emit));
+                    self.pop_scope(result)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Bind `for a, b in ...` names: one name takes the whole item, several
+    /// unpack a list of the same length.
+    fn bind_loop_vars(&mut self, vars: &[String], item: Value) -> Result<()> {
+        if let [var] = vars {
+            self.env.set(var, item);
+            return Ok(());
+        }
+        let parts = item.into_list()?;
+        if parts.len() != vars.len() {
+            return Err(LatchError::ValueError(format!(
+                "cannot unpack {} values into {} names", parts.len(), vars.len()
+            )));
+        }
+        for (var, part) in vars.iter().zip(parts) {
+            self.env.set(var, part);
+        }
+        Ok(())
+    }
+
+    /// Test `subject` against one pattern, returning the names it binds.
+    fn match_pattern(&mut self, pattern: &Pattern, subject: &Value) -> Result<Option<Vec<(String, Value)>>> {
+        Ok(match pattern {
+            Pattern::Wildcard => Some(Vec::new()),
+            Pattern::Binding(name) => Some(vec![(name.clone(), subject.clone())]),
+            Pattern::Literal(expr) => {
+                let literal = self.eval_expr(expr.clone())?;
+                values_equal(&literal, subject).then(Vec::new)
+            }
+            Pattern::Variant { enum_name, variant, bindings } => {
+                let def = match self.env.get(enum_name) {
+                    Some(Value::EnumType(def)) => def.clone(),
+                    _ => return Err(LatchError::UndefinedVariable(enum_name.clone())),
+                };
+                let index = def.variant_index(variant)
+                    .ok_or_else(|| LatchError::KeyNotFound(format!("{enum_name}.{variant}")))?;
+                let Value::Enum(value) = subject else { return Ok(None) };
+                if value.def != def || value.variant != index {
+                    return Ok(None);
+                }
+                match bindings {
+                    None => Some(Vec::new()),
+                    Some(names) => {
+                        if names.len() != value.payload.len() {
+                            return Err(LatchError::ArgCountMismatch {
+                                name: format!("{enum_name}.{variant}"),
+                                expected: value.payload.len(),
+                                found: names.len(),
+                            });
+                        }
+                        Some(names.iter().zip(&value.payload)
+                            .filter(|(name, _)| *name != "_")
+                            .map(|(name, v)| (name.clone(), v.clone()))
+                            .collect())
+                    }
+                }
+            }
+        })
+    }
+
+    /// Run a match arm in its own scope with `bindings` set. `None` if its
+    /// guard rejected the value; block arms evaluate to `null`.
+    fn run_match_arm(&mut self, arm: &MatchArm, bindings: Vec<(String, Value)>) -> Result<Option<Value>> {
+        self.push_scope();
+        for (name, value) in bindings {
+            self.env.set(&name, value);
+        }
+        let mut value = None;
+        let guard = match &arm.guard {
+            Some(guard) => self.eval_expr(guard.clone()).map(|v| v.is_truthy()),
+            None => Ok(true),
+        };
+        let result = guard.and_then(|matched| {
+            if matched {
+                value = Some(match &arm.body {
+                    MatchBody::Expr(expr) => self.eval_expr(expr.clone())?,
+                    MatchBody::Block(block) => {
+                        self.exec_block_inner(block.clone())?;
+                        Value::Null
+                    }
+                });
+            }
+            Ok(())
+        });
+        self.pop_scope(result)?;
+        Ok(value)
+    }
+
+    /// Sort `items` stably by `opts`. Keys are computed once per item; a `cmp`
+    /// function is called for each comparison and must return a number.
+    fn sort_values(&mut self, items: Vec<Value>, opts: &SortOpts) -> Result<Vec<Value>> {
+        let reverse = opts.reverse;
+        let order = move |o: cmp::Ordering| if reverse { o.reverse() } else { o };
+
+        if let Some(func) = &opts.cmp {
+            let Value::Fn { params, body, captured_env } = func else {
+                return Err(LatchError::TypeMismatch { expected: "fn".into(), found: func.type_name().into() });
+            };
+            let captured = captured_env.as_deref().cloned();
+            return merge_sort(items, &mut |a, b| {
+                let result = self.call_closure(params, body, vec![a.clone(), b.clone()], captured.clone())?;
+                Ok(order(comparator_ordering(&result)?))
+            });
+        }
+
+        let Some(key) = &opts.key else {
+            return merge_sort(items, &mut |a, b| Ok(order(compare_values(a, b))));
+        };
+        let mut keyed = Vec::with_capacity(items.len());
+        for item in items {
+            keyed.push((self.sort_key(key, &item)?, item));
+        }
+        let sorted = merge_sort(keyed, &mut |(a, _), (b, _)| Ok(order(compare_values(a, b))))?;
+        Ok(sorted.into_iter().map(|(_, item)| item).collect())
+    }
+
+    /// Evaluate a sort key for `item`: call a fn, read a dict/record field by
+    /// name, or build a list of keys for multi-key ordering.
+    fn sort_key(&mut self, key: &Value, item: &Value) -> Result<Value> {
+        match key {
+            Value::Fn { params, body, captured_env } => {
+                self.call_closure(params, body, vec![item.clone()], captured_env.as_deref().cloned())
+            }
+            Value::Str(field) => Ok(match item {
+                Value::Map(m) => m.lock().unwrap().get(&HashKey::from(field.as_str())).cloned(),
+                other => record_field(other, field),
+            }.unwrap_or(Value::Null)),
+            Value::List(keys) => {
+                let keys = keys.lock().unwrap().clone();
+                let mut parts = Vec::with_capacity(keys.len());
+                for k in &keys {
+                    parts.push(self.sort_key(k, item)?);
+                }
+                Ok(Value::new_list(parts))
+            }
+            other => Err(LatchError::TypeMismatch {
+                expected: "fn, field name, or list of keys".into(),
+                found: other.type_name().into(),
+            }),
+        }
+    }
+
+    /// Bind call arguments to `params` in the current scope. Parameters after
+    /// `...rest` can only be passed by keyword.
+    fn bind_params(&mut self, params: &[Param], args: Vec<Value>, kwargs: &mut KwValues) -> Result<()> {
+        let found = args.len();
+        let mut positional = args.into_iter();
+        let mut rest_seen = false;
+        let mut kwrest = None;
+
+        for param in params {
+            match param.kind {
+                ParamKind::Rest => {
+                    rest_seen = true;
+                    self.env.set(&param.name, Value::new_list(positional.by_ref().collect()));
+                }
+                ParamKind::KwRest => kwrest = Some(&param.name),
+                ParamKind::Normal => {
+                    let keyword = kwargs.iter().position(|(k, _)| *k == param.name).map(|i| kwargs.remove(i).1);
+                    let value = match (if rest_seen { None } else { positional.next() }, keyword) {
+                        (Some(_), Some(_)) => {
+                            return Err(LatchError::TypeError(format!("got multiple values for argument '{}'", param.name)));
+                        }
+                        (Some(v), None) | (None, Some(v)) => v,
+                        (None, None) => match &param.default {
+                            Some(default_expr) => self.eval_expr(default_expr.clone())?,
+                            None => return Err(LatchError::ArgCountMismatch {
+                                name: param.name.clone(),
+                                expected: params.len(),
+                                found,
+                            }),
+                        },
+                    };
+                    self.env.set(&param.name, value);
+                }
+            }
+        }
+
+        let extra = positional.count();
+        if extra > 0 {
+            return Err(LatchError::TypeError(format!(
+                "too many arguments: expected at most {}, got {found}", found - extra
+            )));
+        }
+        match kwrest {
+            Some(name) => self.env.set(name, kwargs_dict(std::mem::take(kwargs))),
+            None => {
+                if let Some((k, _)) = kwargs.first() {
+                    return Err(LatchError::TypeError(format!("unexpected keyword argument '{k}'")));
+                }
+            }
+        }
+        Ok(())
+    }
 }
 
-/// Structural equality for Latch values (used by `in`, `contains`, `==`).
+/// Evaluated keyword arguments, in call order.
+type KwValues = Vec<(String, Value)>;
+
+/// Hidden name in a method's scope for the class defining that method;
+/// `super.m()` looks `m` up from its parent.
+const SUPER_CLASS: &str = "__class__";
+
+/// Collect keyword arguments into a dict (for builtins and `**opts`).
+fn kwargs_dict(kwargs: KwValues) -> Value {
+    Value::new_map(kwargs.into_iter().map(|(k, v)| (k.into(), v)).collect())
+}
+
+/// What a loop does after one run of its body.
+#[derive(PartialEq)]
+enum LoopFlow {
+    Next,
+    Break,
+}
+
+/// Resolve a loop body's result for the loop labeled `label`. An unlabeled
+/// `break`/`continue` targets the innermost loop; a labeled one passes
+/// through loops with other labels until it reaches its own.
+fn loop_control(result: Result<()>, label: &Option<String>) -> Result<LoopFlow> {
+    match result {
+        Ok(()) => Ok(LoopFlow::Next),
+        Err(LatchError::ContinueSignal(target)) if target.is_none() || target == *label => Ok(LoopFlow::Next),
+        Err(LatchError::BreakSignal(target)) if target.is_none() || target == *label => Ok(LoopFlow::Break),
+        Err(e) => Err(e),
+    }
+}
+
+/// Options shared by `sort` and `sorted_by`.
+#[derive(Default)]
+struct SortOpts {
+    key: Option<Value>,
+    cmp: Option<Value>,
+    reverse: bool,
+}
+
+impl SortOpts {
+    /// Read the arguments after the list: a bare key, a bare bool for
+    /// `reverse`, and/or an options dict (which is where `key=`, `cmp=` and
+    /// `reverse=` keyword arguments land).
+    fn from_args(args: &[Value]) -> Result<Self> {
+        let mut opts = SortOpts::default();
+        for arg in args {
+            match arg {
+                Value::Null => {}
+                Value::Bool(reverse) => opts.reverse = *reverse,
+                Value::Map(m) => {
+                    for (k, v) in m.lock().unwrap().iter() {
+                        match k.to_string().as_str() {
+                            "key" => opts.key = Some(v.clone()).filter(|v| !matches!(v, Value::Null)),
+                            "cmp" => opts.cmp = Some(v.clone()).filter(|v| !matches!(v, Value::Null)),
+                            "reverse" => opts.reverse = v.is_truthy(),
+                            other => return Err(LatchError::TypeError(format!("unknown sort option '{other}'"))),
+                        }
+                    }
+                }
+                key => opts.key = Some(key.clone()),
+            }
+        }
+        Ok(opts)
+    }
+}
+
+/// Split `(list, key)` arguments for the `*_by` builtins.
+fn keyed_list_args(name: &str, args: &[Value]) -> Result<(Vec<Value>, Value)> {
+    if args.len() != 2 {
+        return Err(LatchError::ArgCountMismatch { name: name.into(), expected: 2, found: args.len() });
+    }
+    Ok((args[0].as_list()?, args[1].clone()))
+}
+
+/// Stable merge sort with a fallible comparator. Unlike `slice::sort_by` it
+/// tolerates comparators that are not a consistent total order, and stops at
+/// the first error.
+fn merge_sort<T, F>(mut items: Vec<T>, cmp: &mut F) -> Result<Vec<T>>
+where
+    F: FnMut(&T, &T) -> Result<cmp::Ordering>,
+{
+    if items.len() <= 1 {
+        return Ok(items);
+    }
+    let right = items.split_off(items.len() / 2);
+    let left = merge_sort(items, cmp)?;
+    let right = merge_sort(right, cmp)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        // Take from the right only when strictly smaller, keeping equal items in order
+        if cmp(b, a)? == cmp::Ordering::Less {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+/// Interpret a comparator's return value: negative, zero or positive.
+fn comparator_ordering(val: &Value) -> Result<cmp::Ordering> {
+    match val {
+        Value::Int(n) => Ok(n.cmp(&0)),
+        Value::Float(f) => Ok(f.partial_cmp(&0.0).unwrap_or(cmp::Ordering::Equal)),
+        other => Err(LatchError::TypeMismatch {
+            expected: "int from comparator".into(),
+            found: other.type_name().into(),
+        }),
+    }
+}
+
+fn call_module(module: &str, method: &str, args: Vec<Value>) -> Result<Value> {
+    match module {
+        "fs"   => runtime::fs::call(method, args),
+        "proc" => runtime::proc::call(method, args),
+        "http" => runtime::http::call(method, args),
+        "time" => runtime::time::call(method, args),
+        "ai"   => runtime::ai::call(method, args),
+        "json" => runtime::json::call(method, args),
+        "env"  => runtime::env::call(method, args),
+        "path" => runtime::path::call(method, args),
+        "set"  => runtime::set::call(method, args),
+        "bytes" => runtime::bytes::call(method, args),
+        "base64" => runtime::base64::call(method, args),
+        _ => Err(LatchError::UnknownModule(module.to_string())),
+    }
+}
+
+/// Resolve `[start:end:step]` against a sequence of `len` items, returning
+/// the selected positions in order. Negative indices count from the end and
+/// bounds are clamped to the sequence; a negative step walks backwards, with
+/// start and end defaulting to the last and first items.
+fn slice_indices(len: usize, start: Option<i64>, end: Option<i64>, step: Option<i64>) -> Result<impl Iterator<Item = usize>> {
+    let len = len as i64;
+    let step = step.unwrap_or(1);
+    if step == 0 {
+        return Err(LatchError::ValueError("slice step cannot be zero".into()));
+    }
+    let resolve = |i: i64| if i < 0 { len + i } else { i };
+    let (start, end) = if step > 0 {
+        (
+            start.map(resolve).unwrap_or(0).clamp(0, len),
+            end.map(resolve).unwrap_or(len).clamp(0, len),
+        )
+    } else {
+        // -1 stands for "before the first item"
+        (
+            start.map(resolve).unwrap_or(len - 1).clamp(-1, len - 1),
+            end.map(resolve).unwrap_or(-1).clamp(-1, len - 1),
+        )
+    };
+    Ok(std::iter::successors(Some(start), move |i| i.checked_add(step))
+        .take_while(move |&i| if step > 0 { i < end } else { i > end })
+        .map(|i| i as usize))
+}
+
+/// Resolve a possibly negative index into a sequence of `len` items.
+fn resolve_index(index: i64, len: usize) -> Result<usize> {
+    let resolved = if index < 0 { index + len as i64 } else { index };
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(LatchError::IndexOutOfBounds { index, len });
+    }
+    Ok(resolved as usize)
+}
+
+/// Fields of the built-in record values (process results, HTTP responses).
+fn record_field(val: &Value, field: &str) -> Option<Value> {
+    match val {
+        Value::ProcessResult(p) => p.field(field),
+        Value::HttpResponse { status, body, headers } => match field {
+            "status"  => Some(Value::Int(*status)),
+            "body"    => Some(Value::Str(String::from_utf8_lossy(body).into_owned())),
+            "bytes"   => Some(Value::new_bytes(body.clone())),
+            "headers" => {
+                let mut headers: Vec<_> = headers.iter().collect();
+                headers.sort();
+                let map: Dict = headers.into_iter()
+                    .map(|(k, v)| (k.as_str().into(), Value::Str(v.clone())))
+                    .collect();
+                Some(Value::new_map(map))
+            }
+            _ => None,
+        },
+        Value::DateTime(dt) => runtime::time::datetime_field(dt, field),
+        Value::Duration(d) => runtime::time::duration_field(*d, field),
+        Value::Enum(e) => e.field(field),
+        Value::Process(h) => h.field(field),
+        Value::Result(r) => match field {
+            "ok"    => Some(Value::Bool(r.is_ok())),
+            "value" => Some(r.as_ref().map(|v| (**v).clone()).unwrap_or(Value::Null)),
+            "error" => Some(r.as_ref().err().map(|e| (**e).clone()).unwrap_or(Value::Null)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The leading string argument of a string builtin taking at least `expected` args.
+fn str_arg<'a>(name: &str, args: &'a [Value], expected: usize) -> Result<&'a str> {
+    if args.len() < expected {
+        return Err(LatchError::ArgCountMismatch { name: name.into(), expected, found: args.len() });
+    }
+    args[0].as_str()
+}
+
+/// Extract a task handle from a builtin argument.
+fn task_arg(arg: Option<&Value>) -> Result<Arc<TaskState>> {
+    match arg {
+        Some(Value::Task(task)) => Ok(task.clone()),
+        other => Err(LatchError::TypeMismatch {
+            expected: "task".into(),
+            found: other.map(|v| v.type_name()).unwrap_or("none").into(),
+        }),
+    }
+}
+
+/// Parse an optional timeout argument given in milliseconds.
+fn timeout_arg(arg: Option<&Value>) -> Result<Option<Duration>> {
+    match arg {
+        None | Some(Value::Null) => Ok(None),
+        Some(v) => {
+            let ms = v.as_int()?;
+            Ok(Some(Duration::from_millis(ms.max(0) as u64)))
+        }
+    }
+}
+
+/// Structural equality for Latch values. Objects compare by identity here;
+/// `Interpreter::equals` is the version that consults `__eq__`.
 fn values_equal(a: &Value, b: &Value) -> bool {
     match (a, b) {
         (Value::Int(x), Value::Int(y)) => x == y,
@@ -1977,7 +3333,20 @@ fn values_equal(a: &Value, b: &Value) -> bool {
         (Value::Float(x), Value::Int(y)) => *x == (*y as f64),
         (Value::Bool(x), Value::Bool(y)) => x == y,
         (Value::Str(x), Value::Str(y)) => x == y,
+        (Value::Bytes(x), Value::Bytes(y)) => x == y,
+        (Value::DateTime(x), Value::DateTime(y)) => x == y,
+        (Value::Duration(x), Value::Duration(y)) => x == y,
         (Value::Null, Value::Null) => true,
+        (Value::Result(Ok(x)), Value::Result(Ok(y))) | (Value::Result(Err(x)), Value::Result(Err(y))) => values_equal(x, y),
+        (Value::Enum(x), Value::Enum(y)) => {
+            x.def == y.def && x.variant == y.variant
+                && x.payload.iter().zip(&y.payload).all(|(a, b)| values_equal(a, b))
+        }
+        (Value::EnumType(x), Value::EnumType(y)) => Arc::ptr_eq(x, y),
+        (Value::Class(x), Value::Class(y)) => Arc::ptr_eq(x, y),
+        (Value::Interface(x), Value::Interface(y)) => Arc::ptr_eq(x, y),
+        (Value::Instance(x), Value::Instance(y)) => Arc::ptr_eq(x, y),
+        (Value::Process(x), Value::Process(y)) => Arc::ptr_eq(x, y),
         (Value::List(x), Value::List(y)) => {
             let x_guard = x.lock().unwrap();
             let y_guard = y.lock().unwrap();
@@ -1986,6 +3355,7 @@ fn values_equal(a: &Value, b: &Value) -> bool {
             }
             x_guard.iter().zip(y_guard.iter()).all(|(a, b)| values_equal(a, b))
         }
+        (Value::Set(x), Value::Set(y)) => Arc::ptr_eq(x, y) || *x.lock().unwrap() == *y.lock().unwrap(),
         (Value::Map(x), Value::Map(y)) => {
             let x_guard = x.lock().unwrap();
             let y_guard = y.lock().unwrap();
@@ -1999,3 +3369,77 @@ fn values_equal(a: &Value, b: &Value) -> bool {
         _ => false,
     }
 }
+
+/// A total order over all values, used by `sort` and the `*_by` builtins.
+/// Values of different types order by type (null < bool < number < string <
+/// bytes < duration < datetime < list < set < dict < others); ints and floats
+/// compare numerically, and lists, sets and dicts compare element by element.
+fn compare_values(a: &Value, b: &Value) -> cmp::Ordering {
+    fn rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Int(_) | Value::Float(_) => 2,
+            Value::Str(_) => 3,
+            Value::Bytes(_) => 4,
+            Value::Duration(_) => 5,
+            Value::DateTime(_) => 6,
+            Value::List(_) => 7,
+            Value::Set(_) => 8,
+            Value::Map(_) => 9,
+            _ => 10,
+        }
+    }
+    fn compare_seq<'a>(a: impl Iterator<Item = &'a Value>, b: impl Iterator<Item = &'a Value>) -> cmp::Ordering {
+        let mut b = b;
+        for x in a {
+            match b.next() {
+                Some(y) => match compare_values(x, y) {
+                    cmp::Ordering::Equal => {}
+                    ord => return ord,
+                },
+                None => return cmp::Ordering::Greater,
+            }
+        }
+        if b.next().is_some() { cmp::Ordering::Less } else { cmp::Ordering::Equal }
+    }
+
+    match (a, b) {
+        (Value::Int(x), Value::Int(y)) => x.cmp(y),
+        (Value::Float(x), Value::Float(y)) => x.total_cmp(y),
+        (Value::Int(x), Value::Float(y)) => (*x as f64).total_cmp(y),
+        (Value::Float(x), Value::Int(y)) => x.total_cmp(&(*y as f64)),
+        (Value::Bool(x), Value::Bool(y)) => x.cmp(y),
+        (Value::Str(x), Value::Str(y)) => x.cmp(y),
+        (Value::Bytes(x), Value::Bytes(y)) => x.cmp(y),
+        (Value::Duration(x), Value::Duration(y)) => x.cmp(y),
+        (Value::DateTime(x), Value::DateTime(y)) => x.cmp(y),
+        // Variants order by declaration, so `enum Priority { Low, High }` sorts naturally
+        (Value::Enum(x), Value::Enum(y)) if x.def == y.def => {
+            x.variant.cmp(&y.variant).then_with(|| compare_seq(x.payload.iter(), y.payload.iter()))
+        }
+        (Value::List(x), Value::List(y)) => {
+            if Arc::ptr_eq(x, y) {
+                return cmp::Ordering::Equal;
+            }
+            let (x, y) = (x.lock().unwrap().clone(), y.lock().unwrap().clone());
+            compare_seq(x.iter(), y.iter())
+        }
+        (Value::Set(x), Value::Set(y)) => {
+            let x: Vec<Value> = x.lock().unwrap().iter().map(HashKey::to_value).collect();
+            let y: Vec<Value> = y.lock().unwrap().iter().map(HashKey::to_value).collect();
+            compare_seq(x.iter(), y.iter())
+        }
+        (Value::Map(x), Value::Map(y)) => {
+            if Arc::ptr_eq(x, y) {
+                return cmp::Ordering::Equal;
+            }
+            let flatten = |m: &Dict| -> Vec<Value> {
+                m.iter().flat_map(|(k, v)| [k.to_value(), v.clone()]).collect()
+            };
+            let (x, y) = (flatten(&x.lock().unwrap()), flatten(&y.lock().unwrap()));
+            compare_seq(x.iter(), y.iter())
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
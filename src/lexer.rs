@@ -6,9 +6,13 @@ pub enum Token {
     // Literals
     Int(i64),
     Float(f64),
+    /// Duration literal such as `250ms`, `5m` or `1.5h`, in milliseconds
+    Duration(i64),
     Bool(bool),
     Str(String),
     InterpolatedStr(Vec<StringPart>),
+    /// Shell command literal `sh"..."`; its interpolations get shell-quoted
+    ShellStr(Vec<StringPart>),
 
     // Identifier
     Ident(String),
@@ -19,6 +23,7 @@ pub enum Token {
     Plus,     // +
     Minus,    // -
     Star,     // *
+    StarStar, // **
     Slash,    // /
     Percent,  // %
     EqEq,     // ==
@@ -31,8 +36,10 @@ pub enum Token {
     Or,       // ||
     Bang,     // !
     Arrow,    // ->
+    FatArrow, // =>
     Dot,      // .
     DotDot,   // ..
+    Ellipsis, // ...
     Comma,    // ,
     Colon,    // :
     PlusEq,   // +=
@@ -44,6 +51,9 @@ pub enum Token {
     QuestionDot,      // ?.
     Question,         // ? (for ternary)
     PipeGt,   // |>
+    Pipe,     // |
+    Amp,      // &
+    Caret,    // ^
 
     // Grouping
     LBrace,   // {
@@ -79,10 +89,13 @@ pub enum Token {
     KwImport,
     KwStop,
     KwNull,
+    KwSpawn,
+    KwDefer,
+    KwWith,
 
     // Other
     Newline,
-    EOF,
+    Eof,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -139,7 +152,7 @@ impl Lexer {
                         self.advance_newline();
                     }
                     // Only push if last token isn't already a newline
-                    if tokens.last().map_or(true, |t: &Spanned<Token>| t.node != Token::Newline) {
+                    if tokens.last().is_none_or(|t: &Spanned<Token>| t.node != Token::Newline) {
                         tokens.push(Spanned { node: Token::Newline, line, col });
                     }
                 }
@@ -161,6 +174,11 @@ impl Lexer {
                     tokens.push(tok);
                 }
 
+                's' if self.chars[self.pos..].starts_with(&['s', 'h', '"']) => {
+                    let tok = self.lex_shell_string()?;
+                    tokens.push(tok);
+                }
+
                 'a'..='z' | 'A'..='Z' | '_' => {
                     let tok = self.lex_ident_or_keyword();
                     tokens.push(tok);
@@ -185,6 +203,9 @@ impl Lexer {
                     if !self.at_end() && self.peek() == '=' {
                         self.advance();
                         tokens.push(Spanned { node: Token::EqEq, line, col });
+                    } else if !self.at_end() && self.peek() == '>' {
+                        self.advance();
+                        tokens.push(Spanned { node: Token::FatArrow, line, col });
                     } else {
                         tokens.push(Spanned { node: Token::Eq, line, col });
                     }
@@ -234,7 +255,7 @@ impl Lexer {
                         self.advance();
                         tokens.push(Spanned { node: Token::And, line, col });
                     } else {
-                        return Err(LatchError::UnexpectedChar { ch: '&', line, col });
+                        tokens.push(Spanned { node: Token::Amp, line, col });
                     }
                 }
 
@@ -249,10 +270,17 @@ impl Lexer {
                         self.advance();
                         tokens.push(Spanned { node: Token::PipeGt, line, col });
                     } else {
-                        return Err(LatchError::UnexpectedChar { ch: '|', line, col });
+                        tokens.push(Spanned { node: Token::Pipe, line, col });
                     }
                 }
 
+                '^' => {
+                    let line = self.line;
+                    let col = self.col;
+                    self.advance();
+                    tokens.push(Spanned { node: Token::Caret, line, col });
+                }
+
                 '-' => {
                     let line = self.line;
                     let col = self.col;
@@ -286,6 +314,9 @@ impl Lexer {
                     if !self.at_end() && self.peek() == '=' {
                         self.advance();
                         tokens.push(Spanned { node: Token::StarEq, line, col });
+                    } else if !self.at_end() && self.peek() == '*' {
+                        self.advance();
+                        tokens.push(Spanned { node: Token::StarStar, line, col });
                     } else {
                         tokens.push(Spanned { node: Token::Star, line, col });
                     }
@@ -315,6 +346,11 @@ impl Lexer {
                     self.advance();
                     if !self.at_end() && self.peek() == '.' {
                         self.advance();
+                        if !self.at_end() && self.peek() == '.' {
+                            self.advance();
+                            tokens.push(Spanned { node: Token::Ellipsis, line, col });
+                            continue;
+                        }
                         tokens.push(Spanned { node: Token::DotDot, line, col });
                     } else {
                         tokens.push(Spanned { node: Token::Dot, line, col });
@@ -369,7 +405,7 @@ impl Lexer {
             }
         }
 
-        tokens.push(Spanned { node: Token::EOF, line: self.line, col: self.col });
+        tokens.push(Spanned { node: Token::Eof, line: self.line, col: self.col });
         Ok(tokens)
     }
 
@@ -425,6 +461,10 @@ impl Lexer {
 
         // OPTIMIZED: Parse directly from char slice without allocating String
         let num_str: String = self.chars[start..self.pos].iter().collect();
+
+        if let Some(ms) = self.lex_duration_unit(&num_str) {
+            return Spanned { node: Token::Duration(ms), line, col };
+        }
         
         if is_float {
             Spanned { node: Token::Float(num_str.parse().unwrap()), line, col }
@@ -433,6 +473,49 @@ impl Lexer {
         }
     }
 
+    /// A number directly followed by `ms`, `s`, `m`, `h` or `d` is a duration
+    /// literal, optionally with more number-unit pairs (`1h30m`). Consumes the
+    /// units and returns the length in milliseconds.
+    fn lex_duration_unit(&mut self, num_str: &str) -> Option<i64> {
+        let mut total = 0.0;
+        let mut value: f64 = num_str.parse().ok()?;
+        let mut end = self.pos;
+        loop {
+            let unit_start = end;
+            while end < self.chars.len() && self.chars[end].is_ascii_alphabetic() {
+                end += 1;
+            }
+            let unit: String = self.chars[unit_start..end].iter().collect();
+            let factor = match unit.as_str() {
+                "ms" => 1.0,
+                "s" => 1_000.0,
+                "m" => 60_000.0,
+                "h" => 3_600_000.0,
+                "d" => 86_400_000.0,
+                _ => return None,
+            };
+            total += value * factor;
+
+            // Compound literals: `1h30m`, `2m30s500ms`
+            if !(end < self.chars.len() && self.chars[end].is_ascii_digit()) {
+                break;
+            }
+            let num_start = end;
+            while end < self.chars.len() && (self.chars[end].is_ascii_digit() || self.chars[end] == '.') {
+                end += 1;
+            }
+            let num: String = self.chars[num_start..end].iter().collect();
+            value = num.parse().ok()?;
+        }
+        if end < self.chars.len() && (self.chars[end].is_alphanumeric() || self.chars[end] == '_') {
+            return None;
+        }
+        while self.pos < end {
+            self.advance();
+        }
+        Some(total.round() as i64)
+    }
+
     fn lex_ident_or_keyword(&mut self) -> Spanned<Token> {
         let line = self.line;
         let col = self.col;
@@ -470,6 +553,9 @@ impl Lexer {
             "export"   => Token::KwExport,
             "import"   => Token::KwImport,
             "stop"     => Token::KwStop,
+            "spawn"    => Token::KwSpawn,
+            "defer"    => Token::KwDefer,
+            "with"     => Token::KwWith,
             "true"     => Token::Bool(true),
             "false"    => Token::Bool(false),
             "null"     => Token::KwNull,
@@ -479,6 +565,19 @@ impl Lexer {
         Spanned { node: tok, line, col }
     }
 
+    /// `sh"..."`: lexed like a string, kept apart so the parser knows to quote.
+    fn lex_shell_string(&mut self) -> Result<Spanned<Token>> {
+        self.advance(); // skip s
+        self.advance(); // skip h
+        let tok = self.lex_string()?;
+        let parts = match tok.node {
+            Token::Str(s) => vec![StringPart::Literal(s)],
+            Token::InterpolatedStr(parts) => parts,
+            _ => unreachable!(),
+        };
+        Ok(Spanned { node: Token::ShellStr(parts), line: tok.line, col: tok.col - 2 })
+    }
+
     fn lex_string(&mut self) -> Result<Spanned<Token>> {
         let line = self.line;
         let col = self.col;
@@ -3,6 +3,7 @@ mod env;
 mod error;
 mod interpreter;
 mod lexer;
+mod output;
 mod parser;
 mod runtime;
 mod semantic;
@@ -29,6 +30,9 @@ enum Command {
     Run {
         /// Path to the .lt file
         file: String,
+        /// Fail on any command that exits non-zero, as `set strict` does
+        #[arg(long)]
+        strict: bool,
     },
     /// Check a script for errors without running it
     Check {
@@ -59,7 +63,7 @@ fn main() {
     let cli = Cli::parse();
 
     match cli.command {
-        Command::Run { file } => {
+        Command::Run { file, strict } => {
             let source = match std::fs::read_to_string(&file) {
                 Ok(s) => s,
                 Err(e) => {
@@ -99,12 +103,22 @@ fn main() {
             }
 
             // Interpret
-            let mut interp = Interpreter::new();
-            if let Err(e) = interp.run(ast) {
+            // Ctrl-C unwinds the script (running finally/defer) rather than killing it
+            runtime::signal::install();
+            let mut interp = if strict { Interpreter::new().strict() } else { Interpreter::new() };
+            let result = interp.run(ast);
+            // Background processes from proc.spawn don't outlive the script
+            runtime::proc::kill_spawned();
+            if let Err(e) = result {
                 // stop N → clean exit with that code
                 if let LatchError::StopSignal(code) = e {
                     std::process::exit(code);
                 }
+                // Killed by a signal: 130 for SIGINT, 143 for SIGTERM, as shells report it
+                if let LatchError::Interrupted(sig) = e {
+                    print_error(&e, &file, &source);
+                    std::process::exit(128 + sig);
+                }
                 print_error(&e, &file, &source);
                 std::process::exit(1);
             }
@@ -151,6 +165,7 @@ fn main() {
 
         Command::Repl => {
             run_repl();
+            runtime::proc::kill_spawned();
         }
 
         Command::Version => {
@@ -168,7 +183,7 @@ fn run_repl() {
     let stdin = io::stdin();
     let mut interp = Interpreter::new();
 
-    loop {
+    'repl: loop {
         print!("> ");
         io::stdout().flush().ok();
 
@@ -223,7 +238,7 @@ fn run_repl() {
                         Ok(None) => {}
                         Err(LatchError::StopSignal(code)) => {
                             println!("[latch] stop {code}");
-                            return;
+                            break 'repl;
                         }
                         Err(e) => eprintln!("{e}"),
                     }
@@ -232,7 +247,7 @@ fn run_repl() {
                     if let Err(e) = interp.exec_stmt_public(stmt) {
                         if let LatchError::StopSignal(code) = e {
                             println!("[latch] stop {code}");
-                            return;
+                            break 'repl;
                         }
                         eprintln!("{e}");
                     }
@@ -240,4 +255,9 @@ fn run_repl() {
             }
         }
     }
+
+    // `defer`s typed at the prompt run when the session ends
+    if let Err(e) = interp.end_repl() {
+        eprintln!("{e}");
+    }
 }
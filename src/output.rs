@@ -0,0 +1,125 @@
+// ── Script Output ─────────────────────────────────────────────
+// Everything a script prints goes through an `Output` sink so that
+// `parallel` blocks can buffer or prefix their workers' lines.
+// Sinks chain: a worker's sink forwards (possibly buffered) lines to
+// the sink of the interpreter that forked it, ending at the real stdout.
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{LatchError, Result};
+
+/// How a `parallel` block presents its workers' output.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputMode {
+    /// Lines go straight to the terminal as they are printed (default).
+    Interleaved,
+    /// Each item's output is buffered and flushed whole, in input order.
+    Ordered,
+    /// Lines are printed immediately, prefixed with `[item] `.
+    Prefix,
+}
+
+impl OutputMode {
+    pub fn parse(name: &str) -> Result<Self> {
+        match name {
+            "interleaved" => Ok(OutputMode::Interleaved),
+            "ordered" => Ok(OutputMode::Ordered),
+            "prefix" => Ok(OutputMode::Prefix),
+            other => Err(LatchError::GenericError(format!(
+                "parallel: unknown output mode '{other}' (expected \"interleaved\", \"ordered\" or \"prefix\")"
+            ))),
+        }
+    }
+}
+
+/// A buffered line, flagged with whether it belongs on stderr.
+type BufferedLine = (bool, String);
+
+#[derive(Debug, Clone, Default)]
+pub struct Output {
+    prefix: String,
+    buffer: Option<Arc<Mutex<Vec<BufferedLine>>>>,
+    parent: Option<Arc<Output>>,
+}
+
+impl Output {
+    /// Sink for one `parallel` worker processing `item`.
+    pub fn for_worker(&self, mode: OutputMode, item: &str) -> Output {
+        let parent = Some(Arc::new(self.clone()));
+        match mode {
+            OutputMode::Interleaved => self.clone(),
+            OutputMode::Ordered => Output {
+                prefix: String::new(),
+                buffer: Some(Arc::new(Mutex::new(Vec::new()))),
+                parent,
+            },
+            OutputMode::Prefix => Output { prefix: format!("[{item}] "), buffer: None, parent },
+        }
+    }
+
+    /// Print `text` followed by a newline; multi-line text is prefixed per line.
+    pub fn println(&self, text: &str) {
+        for line in text.split('\n') {
+            self.write_line(line, false);
+        }
+    }
+
+    /// Like `println`, but the lines end up on stderr.
+    pub fn eprintln(&self, text: &str) {
+        for line in text.split('\n') {
+            self.write_line(line, true);
+        }
+    }
+
+    fn write_line(&self, line: &str, stderr: bool) {
+        let line = if self.prefix.is_empty() { line.to_string() } else { format!("{}{line}", self.prefix) };
+        if let Some(buf) = &self.buffer {
+            buf.lock().unwrap().push((stderr, line));
+        } else if let Some(parent) = &self.parent {
+            parent.write_line(&line, stderr);
+        } else if stderr {
+            let mut err = std::io::stderr().lock();
+            let _ = writeln!(err, "{line}");
+        } else {
+            let mut out = std::io::stdout().lock();
+            let _ = writeln!(out, "{line}");
+        }
+    }
+
+    /// Forward everything buffered so far to the parent sink in one go.
+    fn flush(&self) {
+        let (Some(buf), Some(parent)) = (&self.buffer, &self.parent) else { return };
+        let lines = std::mem::take(&mut *buf.lock().unwrap());
+        // Hold stdout while writing so another flush can't interleave.
+        let _guard = std::io::stdout().lock();
+        for (stderr, line) in lines {
+            parent.write_line(&line, stderr);
+        }
+    }
+}
+
+/// Releases buffered worker output in input order: item `i` is flushed as
+/// soon as it and every item before it have finished.
+pub struct OrderedFlush {
+    state: Mutex<(usize, Vec<Option<Output>>)>,
+}
+
+impl OrderedFlush {
+    pub fn new(count: usize) -> Self {
+        OrderedFlush { state: Mutex::new((0, vec![None; count])) }
+    }
+
+    pub fn finish(&self, index: usize, output: Output) {
+        let mut state = self.state.lock().unwrap();
+        let (next, slots) = &mut *state;
+        slots[index] = Some(output);
+        while *next < slots.len() {
+            match slots[*next].take() {
+                Some(out) => out.flush(),
+                None => break,
+            }
+            *next += 1;
+        }
+    }
+}
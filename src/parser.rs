@@ -2,15 +2,21 @@ use crate::ast::*;
 use crate::error::{LatchError, Result};
 use crate::lexer::{Lexer, Spanned, StringPart as LexStringPart, Token, TokenStream};
 
+/// Script-wide options accepted by `set`.
+const OPTIONS: &[&str] = &["strict"];
+
 /// Recursive-descent parser: token stream → AST.
 pub struct Parser {
     tokens: TokenStream,
     pos: usize,
+    /// Where the `if`/`while`/`for`/`match` header being parsed starts: at its
+    /// top level a `{` begins the body, never an operand.
+    header_start: Option<usize>,
 }
 
 impl Parser {
     pub fn new(tokens: TokenStream) -> Self {
-        Parser { tokens, pos: 0 }
+        Parser { tokens, pos: 0, header_start: None }
     }
 
     // ── Helpers ──────────────────────────────────────────────
@@ -19,12 +25,18 @@ impl Parser {
         &self.tokens[self.pos].node
     }
 
+    /// Look `offset` tokens ahead without consuming (clamped to EOF).
+    fn peek_at(&self, offset: usize) -> &Token {
+        let idx = (self.pos + offset).min(self.tokens.len() - 1);
+        &self.tokens[idx].node
+    }
+
     fn peek_spanned(&self) -> &Spanned<Token> {
         &self.tokens[self.pos]
     }
 
     fn at_end(&self) -> bool {
-        matches!(self.peek(), Token::EOF)
+        matches!(self.peek(), Token::Eof)
     }
 
     fn advance(&mut self) -> &Spanned<Token> {
@@ -81,16 +93,28 @@ impl Parser {
             Token::KwFn       => self.parse_fn(),
             Token::KwReturn   => self.parse_return(),
             Token::KwStop     => self.parse_stop(),
-            Token::KwTry      => self.parse_try(),
+            Token::KwDefer    => self.parse_defer(),
+            Token::KwWith     => self.parse_with(),
+            Token::KwTry if !matches!(self.peek_at(1), Token::Question) => self.parse_try(),
             Token::KwUse      => self.parse_use(),
             Token::KwWhile    => self.parse_while(),
-            Token::KwBreak    => { self.advance(); Ok(Stmt::Break) },
-            Token::KwContinue => { self.advance(); Ok(Stmt::Continue) },
+            Token::KwBreak    => { self.advance(); Ok(Stmt::Break(self.parse_loop_label())) },
+            Token::KwContinue => { self.advance(); Ok(Stmt::Continue(self.parse_loop_label())) },
             Token::KwConst    => self.parse_const(),
             Token::KwYield    => self.parse_yield(),
             Token::KwClass    => self.parse_class(),
             Token::KwExport   => self.parse_export(),
             Token::KwImport   => self.parse_import(),
+            Token::Ident(_) if self.at_contextual("set") && matches!(self.peek_at(1), Token::Ident(_)) => {
+                self.parse_set_option()
+            }
+            Token::Ident(_) if self.at_contextual("interface")
+                && matches!(self.peek_at(1), Token::Ident(_))
+                && matches!(self.peek_at(2), Token::LBrace) => self.parse_interface(),
+            Token::Ident(_) if self.at_contextual("enum")
+                && matches!(self.peek_at(1), Token::Ident(_))
+                && matches!(self.peek_at(2), Token::LBrace) => self.parse_enum(),
+            Token::Ident(_) if self.at_match() => Ok(Stmt::Expr(self.parse_expr()?)),
             Token::Ident(_)   => self.parse_ident_stmt(),
             _                 => {
                 let expr = self.parse_expr()?;
@@ -104,6 +128,7 @@ impl Parser {
     /// - `name: type := value` (let with annotation)
     /// - `name = value`        (assign)
     /// - `name[idx] = value`   (index assign)
+    /// - `name: for ...` / `name: while ...` (labeled loop)
     /// - `name(...)` or `mod.method(...)` (expression statement)
     fn parse_ident_stmt(&mut self) -> Result<Stmt> {
         let name = match self.advance().node.clone() {
@@ -112,6 +137,19 @@ impl Parser {
         };
 
         match self.peek().clone() {
+            Token::Colon if matches!(self.peek_at(1), Token::KwFor | Token::KwWhile) => {
+                self.advance(); // skip :
+                let mut stmt = if matches!(self.peek(), Token::KwFor) {
+                    self.parse_for()?
+                } else {
+                    self.parse_while()?
+                };
+                if let Stmt::For { label, .. } | Stmt::While { label, .. } = &mut stmt {
+                    *label = Some(name);
+                }
+                Ok(stmt)
+            }
+
             Token::ColonEq => {
                 self.advance(); // skip :=
                 let value = self.parse_expr()?;
@@ -187,14 +225,38 @@ impl Parser {
                 // Rewind so we can re-parse as expression
                 self.pos -= 1;
                 let expr = self.parse_expr()?;
-                Ok(Stmt::Expr(expr))
+                match (expr, self.peek().clone()) {
+                    // obj.field = value
+                    (Expr::FieldAccess { expr: target, field }, Token::Eq) => {
+                        self.advance(); // skip =
+                        let value = self.parse_expr()?;
+                        Ok(Stmt::FieldAssign { target: *target, field, value })
+                    }
+                    // obj.field += value  →  obj.field = obj.field + value
+                    (Expr::FieldAccess { expr: target, field },
+                     Token::PlusEq | Token::MinusEq | Token::StarEq | Token::SlashEq | Token::PercentEq) => {
+                        let op = match self.advance().node.clone() {
+                            Token::PlusEq    => BinOp::Add,
+                            Token::MinusEq   => BinOp::Sub,
+                            Token::StarEq    => BinOp::Mul,
+                            Token::SlashEq   => BinOp::Div,
+                            Token::PercentEq => BinOp::Mod,
+                            _ => unreachable!(),
+                        };
+                        let rhs = self.parse_expr()?;
+                        let current = Expr::FieldAccess { expr: target.clone(), field: field.clone() };
+                        let value = Expr::BinOp { op, left: Box::new(current), right: Box::new(rhs) };
+                        Ok(Stmt::FieldAssign { target: *target, field, value })
+                    }
+                    (expr, _) => Ok(Stmt::Expr(expr)),
+                }
             }
         }
     }
 
     fn parse_if(&mut self) -> Result<Stmt> {
         self.advance(); // skip 'if' or 'elif'
-        let cond = self.parse_expr()?;
+        let cond = self.parse_header_expr()?;
         let then = self.parse_block()?;
 
         self.skip_newlines();
@@ -216,7 +278,7 @@ impl Parser {
     // Helper for elif chains
     fn parse_if_elif(&mut self) -> Result<Stmt> {
         self.advance(); // skip 'elif'
-        let cond = self.parse_expr()?;
+        let cond = self.parse_header_expr()?;
         let then = self.parse_block()?;
 
         self.skip_newlines();
@@ -242,9 +304,31 @@ impl Parser {
             }),
         };
         self.expect(&Token::KwIn)?;
-        let iter = self.parse_expr()?;
+        let iter = self.parse_header_expr()?;
         let body = self.parse_block()?;
-        Ok(Stmt::For { var, iter, body })
+        let else_body = self.parse_loop_else()?;
+        Ok(Stmt::For { label: None, var, iter, body, else_body })
+    }
+
+    /// Optional `else { ... }` after a loop body.
+    fn parse_loop_else(&mut self) -> Result<Option<Block>> {
+        if matches!(self.peek(), Token::KwElse) {
+            self.advance(); // skip 'else'
+            Ok(Some(self.parse_block()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Optional loop label after `break` / `continue`.
+    fn parse_loop_label(&mut self) -> Option<String> {
+        match self.peek().clone() {
+            Token::Ident(label) => {
+                self.advance();
+                Some(label)
+            }
+            _ => None,
+        }
     }
 
     fn parse_parallel(&mut self) -> Result<Stmt> {
@@ -258,17 +342,27 @@ impl Parser {
         self.expect(&Token::KwIn)?;
         let iter = self.parse_expr()?;
 
-        // Optional: workers=N
-        let workers = if matches!(self.peek(), Token::KwWorkers) {
-            self.advance(); // skip 'workers'
-            self.expect(&Token::Eq)?;
-            Some(self.parse_expr()?)
-        } else {
-            None
-        };
+        // Optional, in any order: workers=N  output="ordered"
+        let mut workers = None;
+        let mut output = None;
+        loop {
+            match self.peek() {
+                Token::KwWorkers => {
+                    self.advance(); // skip 'workers'
+                    self.expect(&Token::Eq)?;
+                    workers = Some(self.parse_expr()?);
+                }
+                Token::Ident(n) if n == "output" => {
+                    self.advance(); // skip 'output'
+                    self.expect(&Token::Eq)?;
+                    output = Some(self.parse_expr()?);
+                }
+                _ => break,
+            }
+        }
 
         let body = self.parse_block()?;
-        Ok(Stmt::Parallel { var, iter, workers, body })
+        Ok(Stmt::Parallel { var, iter, workers, output, body })
     }
 
     fn parse_fn(&mut self) -> Result<Stmt> {
@@ -301,12 +395,26 @@ impl Parser {
             return Ok(params);
         }
         loop {
+            // ...rest collects extra positional args, **opts extra keyword args
+            let kind = match self.peek() {
+                Token::Ellipsis => { self.advance(); ParamKind::Rest }
+                Token::StarStar => { self.advance(); ParamKind::KwRest }
+                _ => ParamKind::Normal,
+            };
             let name = match self.advance().node.clone() {
                 Token::Ident(n) => n,
                 other => return Err(LatchError::UnexpectedToken {
                     expected: "parameter name".into(), found: format!("{other:?}"), line: self.line(),
                 }),
             };
+            let seen = |k: ParamKind| params.iter().any(|p: &Param| p.kind == k);
+            if seen(ParamKind::KwRest) || (kind == ParamKind::Rest && seen(ParamKind::Rest)) {
+                return Err(LatchError::UnexpectedToken {
+                    expected: "`**name` as the last parameter and at most one `...name`".into(),
+                    found: name,
+                    line: self.line(),
+                });
+            }
             let type_ann = if matches!(self.peek(), Token::Colon) {
                 self.advance();
                 Some(self.parse_type()?)
@@ -314,13 +422,13 @@ impl Parser {
                 None
             };
             // Check for default value: = expr
-            let default = if matches!(self.peek(), Token::Eq) {
+            let default = if kind == ParamKind::Normal && matches!(self.peek(), Token::Eq) {
                 self.advance(); // skip '='
                 Some(self.parse_expr()?)
             } else {
                 None
             };
-            params.push(Param { name, type_ann, default });
+            params.push(Param { name, type_ann, default, kind });
             if matches!(self.peek(), Token::Comma) {
                 self.advance();
             } else {
@@ -339,6 +447,10 @@ impl Parser {
                 "string"  => Ok(Type::Str),
                 "list"    => Ok(Type::List),
                 "dict"    => Ok(Type::Dict),
+                "set"     => Ok(Type::Set),
+                "bytes"   => Ok(Type::Bytes),
+                "datetime" => Ok(Type::DateTime),
+                "duration" => Ok(Type::Duration),
                 "process" => Ok(Type::Process),
                 "file"    => Ok(Type::File),
                 "any"     => Ok(Type::Any),
@@ -364,6 +476,56 @@ impl Parser {
         Ok(Stmt::Stop(expr))
     }
 
+    /// `set strict` or `set strict = <bool>`. `set` is otherwise the set()
+    /// builtin, so it's only special when a bare name follows.
+    fn parse_set_option(&mut self) -> Result<Stmt> {
+        self.advance(); // skip 'set'
+        let line = self.line();
+        let name = self.expect_ident("option name")?;
+        if !OPTIONS.contains(&name.as_str()) {
+            return Err(LatchError::UnexpectedToken {
+                expected: format!("option name ({})", OPTIONS.join(", ")), found: name, line,
+            });
+        }
+        let value = if matches!(self.peek(), Token::Eq) {
+            self.advance();
+            self.parse_expr()?
+        } else {
+            Expr::Bool(true)
+        };
+        Ok(Stmt::SetOption { name, value })
+    }
+
+    fn parse_defer(&mut self) -> Result<Stmt> {
+        self.advance(); // skip 'defer'
+        let body = if matches!(self.peek(), Token::LBrace) {
+            self.parse_block()?
+        } else {
+            vec![Stmt::Expr(self.parse_expr()?)]
+        };
+        Ok(Stmt::Defer(body))
+    }
+
+    fn parse_with(&mut self) -> Result<Stmt> {
+        self.advance(); // skip 'with'
+        let resource = self.parse_expr()?;
+        // Optional: as name
+        let name = match self.peek() {
+            Token::Ident(n) if n == "as" => {
+                self.advance(); // skip 'as'
+                match self.advance().node.clone() {
+                    Token::Ident(n) => Some(n),
+                    other => return Err(LatchError::UnexpectedToken {
+                        expected: "identifier".into(), found: format!("{other:?}"), line: self.line(),
+                    }),
+                }
+            }
+            _ => None,
+        };
+        let body = self.parse_block()?;
+        Ok(Stmt::With { resource, name, body })
+    }
+
     fn parse_try(&mut self) -> Result<Stmt> {
         self.advance(); // skip 'try'
         let body = self.parse_block()?;
@@ -390,9 +552,10 @@ impl Parser {
 
     fn parse_while(&mut self) -> Result<Stmt> {
         self.advance(); // skip 'while'
-        let cond = self.parse_expr()?;
+        let cond = self.parse_header_expr()?;
         let body = self.parse_block()?;
-        Ok(Stmt::While { cond, body })
+        let else_body = self.parse_loop_else()?;
+        Ok(Stmt::While { label: None, cond, body, else_body })
     }
 
     fn parse_const(&mut self) -> Result<Stmt> {
@@ -425,35 +588,234 @@ impl Parser {
         Ok(Stmt::Yield(expr))
     }
 
+    /// `class Name [extends Parent] [implements A, B] { ... }` — the body holds
+    /// fields (`port: int`, `port: int := 80`, `port := 80`) and `fn` methods,
+    /// separated by newlines or commas.
     fn parse_class(&mut self) -> Result<Stmt> {
         self.advance(); // skip 'class'
-        let name = match self.advance().node.clone() {
-            Token::Ident(n) => n,
-            other => return Err(LatchError::UnexpectedToken {
-                expected: "class name".into(),
-                found: format!("{:?}", other),
-                line: self.line(),
-            }),
+        let name = self.expect_ident("class name")?;
+
+        let parent = if self.at_contextual("extends") {
+            self.advance();
+            Some(self.expect_ident("parent class name")?)
+        } else {
+            None
         };
-        
-        let body = self.parse_block()?;
+        let mut interfaces = Vec::new();
+        if self.at_contextual("implements") {
+            self.advance();
+            interfaces.push(self.expect_ident("interface name")?);
+            while matches!(self.peek(), Token::Comma) {
+                self.advance();
+                interfaces.push(self.expect_ident("interface name")?);
+            }
+        }
+
+        self.skip_newlines();
+        self.expect(&Token::LBrace)?;
         let mut fields = Vec::new();
         let mut methods = Vec::new();
-        
-        // Parse class body
-        for stmt in body {
-            match stmt {
-                Stmt::Let { name, type_ann, value } => {
-                    fields.push((name, type_ann, Some(value)));
-                }
-                Stmt::Fn { name, params, return_type: _, body } => {
+        self.skip_newlines();
+        while !matches!(self.peek(), Token::RBrace | Token::Eof) {
+            if matches!(self.peek(), Token::KwFn) {
+                if let Stmt::Fn { name, params, body, .. } = self.parse_fn()? {
                     methods.push((name, params, body));
                 }
-                _ => {}
+            } else {
+                let field = self.expect_ident("field name or 'fn'")?;
+                let type_ann = if matches!(self.peek(), Token::Colon) {
+                    self.advance();
+                    Some(self.parse_type()?)
+                } else {
+                    None
+                };
+                let default = if type_ann.is_none() || matches!(self.peek(), Token::ColonEq) {
+                    self.expect(&Token::ColonEq)?;
+                    Some(self.parse_expr()?)
+                } else {
+                    None
+                };
+                fields.push((field, type_ann, default));
+            }
+            self.skip_newlines();
+            if matches!(self.peek(), Token::Comma) {
+                self.advance();
+                self.skip_newlines();
             }
         }
-        
-        Ok(Stmt::Class { name, fields, methods })
+        self.expect(&Token::RBrace)?;
+
+        Ok(Stmt::Class { name, parent, interfaces, fields, methods })
+    }
+
+    /// `interface Name { fn method(params) ... }` — signatures only, separated
+    /// by newlines or commas.
+    fn parse_interface(&mut self) -> Result<Stmt> {
+        self.advance(); // skip 'interface'
+        let name = self.expect_ident("interface name")?;
+        self.expect(&Token::LBrace)?;
+        let mut methods = Vec::new();
+        self.skip_newlines();
+        while !matches!(self.peek(), Token::RBrace | Token::Eof) {
+            self.expect(&Token::KwFn)?;
+            let method = self.expect_ident("method name")?;
+            self.expect(&Token::LParen)?;
+            let params = self.parse_params()?;
+            self.expect(&Token::RParen)?;
+            if matches!(self.peek(), Token::Arrow) {
+                self.advance();
+                self.parse_type()?;
+            }
+            methods.push((method, params));
+            self.skip_newlines();
+            if matches!(self.peek(), Token::Comma) {
+                self.advance();
+                self.skip_newlines();
+            }
+        }
+        self.expect(&Token::RBrace)?;
+        Ok(Stmt::Interface { name, methods })
+    }
+
+    /// Is the next token the plain word `word`? `extends`, `implements`,
+    /// `interface`, `enum` and `match` are only special in declarations and
+    /// match expressions, so they stay usable as names.
+    fn at_contextual(&self, word: &str) -> bool {
+        matches!(self.peek(), Token::Ident(w) if w == word)
+    }
+
+    /// Does a match expression start here? `match` is only a keyword when a
+    /// subject and `{` follow it, so `match := m[0]` still assigns a variable.
+    fn at_match(&mut self) -> bool {
+        if !self.at_contextual("match") {
+            return false;
+        }
+        let saved = self.pos;
+        self.advance(); // skip 'match'
+        let is_match = self.parse_header_expr().is_ok() && matches!(self.peek(), Token::LBrace);
+        self.pos = saved;
+        is_match
+    }
+
+    /// `enum Name { A, B(field, ...), ... }` — commas or newlines between variants.
+    fn parse_enum(&mut self) -> Result<Stmt> {
+        self.advance(); // skip 'enum'
+        let name = self.expect_ident("enum name")?;
+        self.expect(&Token::LBrace)?;
+        let mut variants = Vec::new();
+        self.skip_newlines();
+        while !matches!(self.peek(), Token::RBrace | Token::Eof) {
+            let variant = self.expect_ident("variant name")?;
+            let mut fields = Vec::new();
+            if matches!(self.peek(), Token::LParen) {
+                self.advance();
+                while !matches!(self.peek(), Token::RParen | Token::Eof) {
+                    fields.push(self.expect_ident("payload field name")?);
+                    if matches!(self.peek(), Token::Comma) {
+                        self.advance();
+                    }
+                }
+                self.expect(&Token::RParen)?;
+            }
+            variants.push(EnumVariant { name: variant, fields });
+            self.skip_newlines();
+            if matches!(self.peek(), Token::Comma) {
+                self.advance();
+                self.skip_newlines();
+            }
+        }
+        self.expect(&Token::RBrace)?;
+        Ok(Stmt::Enum { name, variants })
+    }
+
+    /// `match subject { arm ... }` — arms separated by newlines or commas.
+    fn parse_match(&mut self) -> Result<Expr> {
+        self.advance(); // skip 'match'
+        let subject = self.parse_header_expr()?;
+        self.expect(&Token::LBrace)?;
+        let mut arms = Vec::new();
+        self.skip_newlines();
+        while !matches!(self.peek(), Token::RBrace | Token::Eof) {
+            let mut patterns = vec![self.parse_pattern()?];
+            while matches!(self.peek(), Token::Pipe) {
+                self.advance();
+                patterns.push(self.parse_pattern()?);
+            }
+            let guard = if matches!(self.peek(), Token::KwIf) {
+                self.advance();
+                Some(self.parse_expr()?)
+            } else {
+                None
+            };
+            self.expect(&Token::FatArrow)?;
+            let body = if matches!(self.peek(), Token::LBrace) {
+                MatchBody::Block(self.parse_block()?)
+            } else {
+                MatchBody::Expr(self.parse_expr()?)
+            };
+            arms.push(MatchArm { patterns, guard, body });
+            self.skip_newlines();
+            if matches!(self.peek(), Token::Comma) {
+                self.advance();
+                self.skip_newlines();
+            }
+        }
+        self.expect(&Token::RBrace)?;
+        Ok(Expr::Match { subject: Box::new(subject), arms })
+    }
+
+    fn parse_pattern(&mut self) -> Result<Pattern> {
+        match self.peek().clone() {
+            Token::Ident(n) if n == "_" => {
+                self.advance();
+                Ok(Pattern::Wildcard)
+            }
+            Token::Ident(enum_name) if matches!(self.peek_at(1), Token::Dot) => {
+                self.advance();
+                self.advance(); // skip .
+                let variant = self.expect_ident("variant name")?;
+                let bindings = if matches!(self.peek(), Token::LParen) {
+                    self.advance();
+                    let mut names = Vec::new();
+                    while !matches!(self.peek(), Token::RParen | Token::Eof) {
+                        names.push(self.expect_ident("binding name")?);
+                        if matches!(self.peek(), Token::Comma) {
+                            self.advance();
+                        }
+                    }
+                    self.expect(&Token::RParen)?;
+                    Some(names)
+                } else {
+                    None
+                };
+                Ok(Pattern::Variant { enum_name, variant, bindings })
+            }
+            Token::Ident(n) => {
+                self.advance();
+                Ok(Pattern::Binding(n))
+            }
+            Token::Int(_) | Token::Float(_) | Token::Str(_) | Token::Bool(_) | Token::KwNull
+            | Token::Duration(_) | Token::Minus => Ok(Pattern::Literal(self.parse_unary()?)),
+            _ => {
+                let sp = self.peek_spanned();
+                Err(LatchError::UnexpectedToken {
+                    expected: "pattern".into(),
+                    found: format!("{:?}", sp.node),
+                    line: sp.line,
+                })
+            }
+        }
+    }
+
+    fn expect_ident(&mut self, what: &str) -> Result<String> {
+        match self.advance().node.clone() {
+            Token::Ident(n) => Ok(n),
+            other => Err(LatchError::UnexpectedToken {
+                expected: what.into(),
+                found: format!("{other:?}"),
+                line: self.line(),
+            }),
+        }
     }
 
     fn parse_export(&mut self) -> Result<Stmt> {
@@ -463,7 +825,7 @@ impl Parser {
         if matches!(self.peek(), Token::LBrace) {
             self.advance(); // skip '{'
             let mut names = Vec::new();
-            while !matches!(self.peek(), Token::RBrace | Token::EOF) {
+            while !matches!(self.peek(), Token::RBrace | Token::Eof) {
                 let name = match self.advance().node.clone() {
                     Token::Ident(n) => n,
                     other => return Err(LatchError::UnexpectedToken {
@@ -500,7 +862,7 @@ impl Parser {
         let items = if matches!(self.peek(), Token::LBrace) {
             self.advance(); // skip '{'
             let mut names = Vec::new();
-            while !matches!(self.peek(), Token::RBrace | Token::EOF) {
+            while !matches!(self.peek(), Token::RBrace | Token::Eof) {
                 let name = match self.advance().node.clone() {
                     Token::Ident(n) => n,
                     other => return Err(LatchError::UnexpectedToken {
@@ -568,7 +930,7 @@ impl Parser {
         self.expect(&Token::LBrace)?;
         let mut stmts = Vec::new();
         self.skip_newlines();
-        while !matches!(self.peek(), Token::RBrace | Token::EOF) {
+        while !matches!(self.peek(), Token::RBrace | Token::Eof) {
             stmts.push(self.parse_stmt()?);
             self.skip_newlines();
         }
@@ -689,7 +1051,7 @@ impl Parser {
     }
 
     fn parse_comparison(&mut self) -> Result<Expr> {
-        let mut left = self.parse_range()?;
+        let mut left = self.parse_bit_or()?;
         loop {
             let op = match self.peek() {
                 Token::Lt   => BinOp::Lt,
@@ -700,12 +1062,42 @@ impl Parser {
                 _ => break,
             };
             self.advance();
-            let right = self.parse_range()?;
+            let right = self.parse_bit_or()?;
             left = Expr::BinOp { op, left: Box::new(left), right: Box::new(right) };
         }
         Ok(left)
     }
 
+    fn parse_bit_or(&mut self) -> Result<Expr> {
+        let mut left = self.parse_bit_xor()?;
+        while matches!(self.peek(), Token::Pipe) {
+            self.advance();
+            let right = self.parse_bit_xor()?;
+            left = Expr::BinOp { op: BinOp::BitOr, left: Box::new(left), right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
+    fn parse_bit_xor(&mut self) -> Result<Expr> {
+        let mut left = self.parse_bit_and()?;
+        while matches!(self.peek(), Token::Caret) {
+            self.advance();
+            let right = self.parse_bit_and()?;
+            left = Expr::BinOp { op: BinOp::BitXor, left: Box::new(left), right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
+    fn parse_bit_and(&mut self) -> Result<Expr> {
+        let mut left = self.parse_range()?;
+        while matches!(self.peek(), Token::Amp) {
+            self.advance();
+            let right = self.parse_range()?;
+            left = Expr::BinOp { op: BinOp::BitAnd, left: Box::new(left), right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
     fn parse_range(&mut self) -> Result<Expr> {
         let left = self.parse_additive()?;
         if matches!(self.peek(), Token::DotDot) {
@@ -781,6 +1173,7 @@ impl Parser {
                     self.advance();
                     let field = match self.advance().node.clone() {
                         Token::Ident(n) => n,
+                        Token::KwSpawn => "spawn".into(), // proc.spawn(...)
                         other => return Err(LatchError::UnexpectedToken {
                             expected: "field name".into(), found: format!("{other:?}"), line: self.line(),
                         }),
@@ -788,24 +1181,20 @@ impl Parser {
 
                     if matches!(self.peek(), Token::LParen) {
                         // This is a method/module call: expr.method(args)
-                        // We only support: ident.method(args) for module calls
                         self.advance(); // skip (
-                        let args = self.parse_args()?;
+                        let (args, kwargs) = self.parse_args()?;
                         self.expect(&Token::RParen)?;
 
-                        if let Expr::Ident(module) = expr {
-                            expr = Expr::ModuleCall { module, method: field, args };
-                        } else {
-                            return Err(LatchError::GenericError(
-                                "Method calls are only supported on module names".into(),
-                            ));
-                        }
+                        expr = match expr {
+                            Expr::Ident(module) => Expr::ModuleCall { module, method: field, args, kwargs },
+                            object => Expr::MethodCall { object: Box::new(object), method: field, args, kwargs },
+                        };
                     } else {
                         expr = Expr::FieldAccess { expr: Box::new(expr), field };
                     }
                 }
 
-                // index: expr[index] or slice: expr[start:end]
+                // index: expr[index] or slice: expr[start:end:step]
                 Token::LBracket => {
                     self.advance();
                     
@@ -841,14 +1230,26 @@ impl Parser {
                         
                         self.expect(&Token::Colon)?;
                         
-                        let end = if matches!(self.peek(), Token::RBracket) {
+                        let end = if matches!(self.peek(), Token::RBracket | Token::Colon) {
                             None
                         } else {
                             Some(Box::new(self.parse_expr()?))
                         };
+
+                        // Optional step: start:end:step
+                        let step = if matches!(self.peek(), Token::Colon) {
+                            self.advance();
+                            if matches!(self.peek(), Token::RBracket) {
+                                None
+                            } else {
+                                Some(Box::new(self.parse_expr()?))
+                            }
+                        } else {
+                            None
+                        };
                         
                         self.expect(&Token::RBracket)?;
-                        expr = Expr::Slice { expr: Box::new(expr), start, end };
+                        expr = Expr::Slice { expr: Box::new(expr), start, end, step };
                     } else {
                         // Parse regular index
                         let index = self.parse_expr()?;
@@ -857,6 +1258,12 @@ impl Parser {
                     }
                 }
 
+                // propagate: expr? — only when `?` can't be the start of a ternary
+                Token::Question if self.at_postfix_question() => {
+                    self.advance();
+                    expr = Expr::Propagate(Box::new(expr));
+                }
+
                 // safe access: expr?.field
                 Token::QuestionDot => {
                     self.advance();
@@ -872,10 +1279,10 @@ impl Parser {
                 // call: expr(args) — only for Ident
                 Token::LParen if matches!(expr, Expr::Ident(_)) => {
                     self.advance();
-                    let args = self.parse_args()?;
+                    let (args, kwargs) = self.parse_args()?;
                     self.expect(&Token::RParen)?;
                     if let Expr::Ident(name) = expr {
-                        expr = Expr::Call { name, args, kwargs: Vec::new() };
+                        expr = Expr::Call { name, args, kwargs };
                     }
                 }
 
@@ -886,20 +1293,92 @@ impl Parser {
         Ok(expr)
     }
 
-    fn parse_args(&mut self) -> Result<Vec<Expr>> {
+    /// Call arguments: positional (`x`, `...xs`) followed by keyword (`name=x`).
+    fn parse_args(&mut self) -> Result<(Vec<Expr>, KwArgs)> {
         let mut args = Vec::new();
+        let mut kwargs = Vec::new();
         if matches!(self.peek(), Token::RParen) {
-            return Ok(args);
+            return Ok((args, kwargs));
         }
         loop {
-            args.push(self.parse_expr()?);
+            if let (Token::Ident(name), Token::Eq) = (self.peek().clone(), self.peek_at(1)) {
+                self.advance(); // name
+                self.advance(); // =
+                kwargs.push((name, self.parse_expr()?));
+            } else if !kwargs.is_empty() {
+                return Err(LatchError::UnexpectedToken {
+                    expected: "keyword argument (positional arguments must come first)".into(),
+                    found: format!("{:?}", self.peek()),
+                    line: self.line(),
+                });
+            } else {
+                args.push(self.parse_spread_or_expr()?);
+            }
             if matches!(self.peek(), Token::Comma) {
                 self.advance();
             } else {
                 break;
             }
         }
-        Ok(args)
+        Ok((args, kwargs))
+    }
+
+    /// A dict literal key: a bare name or string is taken literally,
+    /// `[expr]` is computed, `[a, b]` is the tuple of both values, and
+    /// anything else is an expression (`1`, `true`).
+    fn parse_map_key(&mut self) -> Result<Expr> {
+        match self.peek().clone() {
+            Token::Str(s) | Token::Ident(s) if matches!(self.peek_at(1), Token::Colon) => {
+                self.advance();
+                Ok(Expr::Str(s))
+            }
+            Token::LBracket if self.at_computed_key() => {
+                self.advance(); // skip [
+                let mut items = vec![self.parse_expr()?];
+                // `[host, port]: v` is keyed by the tuple (list) of the values
+                while matches!(self.peek(), Token::Comma) {
+                    self.advance();
+                    items.push(self.parse_expr()?);
+                }
+                self.expect(&Token::RBracket)?;
+                Ok(match items.len() {
+                    1 => items.remove(0),
+                    _ => Expr::List(items),
+                })
+            }
+            _ => self.parse_expr(),
+        }
+    }
+
+    /// Is the parser at `[ ... ]:`, a computed dict key?
+    fn at_computed_key(&self) -> bool {
+        if !matches!(self.peek(), Token::LBracket) {
+            return false;
+        }
+        let mut depth = 0;
+        for offset in 0.. {
+            match self.peek_at(offset) {
+                Token::LBracket => depth += 1,
+                Token::RBracket => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return matches!(self.peek_at(offset + 1), Token::Colon);
+                    }
+                }
+                Token::Eof => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// `...expr` or a plain expression — for call arguments and list elements.
+    fn parse_spread_or_expr(&mut self) -> Result<Expr> {
+        if matches!(self.peek(), Token::Ellipsis) {
+            self.advance();
+            return Ok(Expr::Spread(Box::new(self.parse_expr()?)));
+        }
+        self.parse_expr()
     }
 
     fn parse_primary(&mut self) -> Result<Expr> {
@@ -907,9 +1386,18 @@ impl Parser {
         match tok {
             Token::Int(n)    => { self.advance(); Ok(Expr::Int(n)) }
             Token::Float(n)  => { self.advance(); Ok(Expr::Float(n)) }
+            Token::Duration(ms) => { self.advance(); Ok(Expr::Duration(ms)) }
             Token::Bool(b)   => { self.advance(); Ok(Expr::Bool(b)) }
             Token::Str(s)    => { self.advance(); Ok(Expr::Str(s)) }
             Token::KwNull    => { self.advance(); Ok(Expr::Null) }
+            Token::Ident(_) if self.at_match() => self.parse_match(),
+            // Arrow lambda with one parameter: x => x * 2
+            Token::Ident(n) if matches!(self.peek_at(1), Token::FatArrow) => {
+                self.advance();
+                self.advance(); // skip =>
+                let param = Param { name: n, type_ann: None, default: None, kind: ParamKind::Normal };
+                self.parse_arrow_body(vec![param])
+            }
             Token::Ident(n)  => { self.advance(); Ok(Expr::Ident(n)) }
 
             Token::InterpolatedStr(parts) => {
@@ -918,6 +1406,12 @@ impl Parser {
                 Ok(Expr::Interpolated(ast_parts))
             }
 
+            Token::ShellStr(parts) => {
+                self.advance();
+                let ast_parts = self.convert_interpolation(parts)?;
+                Ok(Expr::ShellCommand(ast_parts))
+            }
+
             Token::LBracket => {
                 self.advance(); // skip [
                 
@@ -927,41 +1421,17 @@ impl Parser {
                 self.pos = saved_pos;
                 
                 if is_comprehension {
-                    // Parse list comprehension: [expr for var in iter if cond]
+                    // Parse list comprehension: [expr for var in iter if cond ...]
                     let body = self.parse_expr()?;
-                    self.expect(&Token::KwFor)?;
-                    let var = match self.advance().node.clone() {
-                        Token::Ident(n) => n,
-                        other => return Err(LatchError::UnexpectedToken {
-                            expected: "loop variable".into(),
-                            found: format!("{:?}", other),
-                            line: self.line(),
-                        }),
-                    };
-                    self.expect(&Token::KwIn)?;
-                    let iter = self.parse_expr()?;
-                    
-                    // Optional condition: if expr
-                    let cond = if matches!(self.peek(), Token::KwIf) {
-                        self.advance(); // skip 'if'
-                        Some(Box::new(self.parse_expr()?))
-                    } else {
-                        None
-                    };
-                    
+                    let clauses = self.parse_comp_clauses()?;
                     self.expect(&Token::RBracket)?;
-                    Ok(Expr::ListComp { 
-                        body: Box::new(body), 
-                        var, 
-                        iter: Box::new(iter), 
-                        cond 
-                    })
+                    Ok(Expr::ListComp { body: Box::new(body), clauses })
                 } else {
                     // Parse regular list literal
                     let mut elems = Vec::new();
                     self.skip_newlines();
-                    while !matches!(self.peek(), Token::RBracket | Token::EOF) {
-                        elems.push(self.parse_expr()?);
+                    while !matches!(self.peek(), Token::RBracket | Token::Eof) {
+                        elems.push(self.parse_spread_or_expr()?);
                         self.skip_newlines();
                         if matches!(self.peek(), Token::Comma) {
                             self.advance();
@@ -974,23 +1444,75 @@ impl Parser {
             }
 
             Token::LBrace => {
-                // Map literal: {"key": value, "key2": value2}
                 self.advance(); // skip {
-                let mut entries = Vec::new();
                 self.skip_newlines();
-                while !matches!(self.peek(), Token::RBrace | Token::EOF) {
-                    let key = match self.advance().node.clone() {
-                        Token::Str(s) => s,
-                        Token::Ident(s) => s,
-                        other => return Err(LatchError::UnexpectedToken {
-                            expected: "string or identifier key".into(),
-                            found: format!("{other:?}"),
-                            line: self.line(),
-                        }),
-                    };
-                    self.expect(&Token::Colon)?;
+
+                // `{}`, `{...d}`, `{name: v}` and `{[expr]: v}` are dicts. Otherwise
+                // read the first expression: a `:` after it makes a dict keyed by
+                // that value (`{1: "a"}`), anything else a set (`{1, 2, 3}`).
+                let mut entries = Vec::new();
+                let starts_dict = matches!(self.peek(), Token::RBrace | Token::Ellipsis)
+                    || (matches!(self.peek(), Token::Str(_) | Token::Ident(_))
+                        && matches!(self.peek_at(1), Token::Colon))
+                    || self.at_computed_key();
+                if !starts_dict {
+                    let first = self.parse_expr()?;
+                    self.skip_newlines();
+                    if matches!(self.peek(), Token::KwFor) {
+                        let clauses = self.parse_comp_clauses()?;
+                        self.expect(&Token::RBrace)?;
+                        return Ok(Expr::SetComp { body: Box::new(first), clauses });
+                    }
+                    if !matches!(self.peek(), Token::Colon) {
+                        let mut items = vec![first];
+                        self.skip_newlines();
+                        while matches!(self.peek(), Token::Comma) {
+                            self.advance();
+                            self.skip_newlines();
+                            if matches!(self.peek(), Token::RBrace) {
+                                break;
+                            }
+                            items.push(self.parse_expr()?);
+                            self.skip_newlines();
+                        }
+                        self.expect(&Token::RBrace)?;
+                        return Ok(Expr::Set(items));
+                    }
+                    self.advance(); // skip :
                     let value = self.parse_expr()?;
-                    entries.push((key, value));
+                    self.skip_newlines();
+                    if matches!(self.peek(), Token::KwFor) {
+                        return self.finish_dict_comp(first, value);
+                    }
+                    entries.push(MapEntry::Pair(first, value));
+                    self.skip_newlines();
+                    if matches!(self.peek(), Token::Comma) {
+                        self.advance();
+                        self.skip_newlines();
+                    }
+                }
+
+                // Map literal: {"key": value, name: value, 1: value, [expr]: value}
+                while !matches!(self.peek(), Token::RBrace | Token::Eof) {
+                    if matches!(self.peek(), Token::Ellipsis) {
+                        self.advance();
+                        entries.push(MapEntry::Spread(self.parse_expr()?));
+                    } else {
+                        let bare_name = match (self.peek(), self.peek_at(1)) {
+                            (Token::Ident(n), Token::Colon) => Some(n.clone()),
+                            _ => None,
+                        };
+                        let key = self.parse_map_key()?;
+                        self.expect(&Token::Colon)?;
+                        let value = self.parse_expr()?;
+                        self.skip_newlines();
+                        if entries.is_empty() && matches!(self.peek(), Token::KwFor) {
+                            // In a comprehension a bare key is the loop variable, not a string
+                            let key = bare_name.map(Expr::Ident).unwrap_or(key);
+                            return self.finish_dict_comp(key, value);
+                        }
+                        entries.push(MapEntry::Pair(key, value));
+                    }
                     self.skip_newlines();
                     if matches!(self.peek(), Token::Comma) {
                         self.advance();
@@ -1001,6 +1523,15 @@ impl Parser {
                 Ok(Expr::Map(entries))
             }
 
+            // Arrow lambda: (a, b) => a + b, () => 42
+            Token::LParen if self.at_paren_lambda() => {
+                self.advance(); // skip (
+                let params = self.parse_params()?;
+                self.expect(&Token::RParen)?;
+                self.expect(&Token::FatArrow)?;
+                self.parse_arrow_body(params)
+            }
+
             Token::LParen => {
                 self.advance();
                 let expr = self.parse_expr()?;
@@ -1008,6 +1539,14 @@ impl Parser {
                 Ok(expr)
             }
 
+            // try? expr — capture success or failure as a result value. Covers the
+            // rest of the expression up to `or` or a ternary.
+            Token::KwTry if matches!(self.peek_at(1), Token::Question) => {
+                self.advance(); // skip 'try'
+                self.advance(); // skip '?'
+                Ok(Expr::TryResult(Box::new(self.parse_pipe()?)))
+            }
+
             // Anonymous function: fn(x, y) { ... }
             Token::KwFn => {
                 self.advance(); // skip 'fn'
@@ -1018,6 +1557,18 @@ impl Parser {
                 Ok(Expr::Fn { params, body })
             }
 
+            // Background task: spawn fn() { ... } or spawn { ... }
+            Token::KwSpawn => {
+                self.advance(); // skip 'spawn'
+                let func = if matches!(self.peek(), Token::LBrace) {
+                    let body = self.parse_block()?;
+                    Expr::Fn { params: vec![], body }
+                } else {
+                    self.parse_unary()?
+                };
+                Ok(Expr::Spawn(Box::new(func)))
+            }
+
             _ => {
                 let sp = self.peek_spanned();
                 Err(LatchError::UnexpectedToken {
@@ -1029,6 +1580,118 @@ impl Parser {
         }
     }
 
+    /// Is the `?` at the current position postfix propagation (`expr?`)
+    /// rather than a ternary? It is when the next token can't start an
+    /// expression, or is the `{` opening a block after a header (`if x? {`).
+    fn at_postfix_question(&self) -> bool {
+        match self.peek_at(1) {
+            Token::LBrace => self.at_header_level(),
+            next => matches!(
+                next,
+                Token::Newline | Token::Eof | Token::RParen | Token::RBracket | Token::RBrace |
+                Token::Comma | Token::Dot | Token::KwOr | Token::PipeGt | Token::QuestionQuestion |
+                Token::Plus | Token::Star | Token::Slash | Token::Percent |
+                Token::EqEq | Token::NotEq | Token::Lt | Token::Gt | Token::LtEq | Token::GtEq |
+                Token::And | Token::Or
+            ),
+        }
+    }
+
+    /// The expression after `if`, `while`, `for x in` or `match`, which the
+    /// block's `{` ends.
+    fn parse_header_expr(&mut self) -> Result<Expr> {
+        let outer = self.header_start.replace(self.pos);
+        let expr = self.parse_expr();
+        self.header_start = outer;
+        expr
+    }
+
+    /// Is the parser inside a header but outside any brackets opened in it?
+    fn at_header_level(&self) -> bool {
+        let Some(start) = self.header_start else { return false };
+        let mut depth = 0;
+        for tok in &self.tokens[start..self.pos] {
+            match tok.node {
+                Token::LParen | Token::LBracket | Token::LBrace => depth += 1,
+                Token::RParen | Token::RBracket | Token::RBrace => depth -= 1,
+                _ => {}
+            }
+        }
+        depth == 0
+    }
+
+    /// The body of an arrow lambda is a single expression, returned as-is.
+    fn parse_arrow_body(&mut self, params: Vec<Param>) -> Result<Expr> {
+        let body = self.parse_expr()?;
+        Ok(Expr::Fn { params, body: vec![Stmt::Return(body)] })
+    }
+
+    /// Is the parser at `( ... ) =>`, the parameter list of an arrow lambda?
+    fn at_paren_lambda(&self) -> bool {
+        let mut depth = 0;
+        for offset in 0.. {
+            match self.peek_at(offset) {
+                Token::LParen => depth += 1,
+                Token::RParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return matches!(self.peek_at(offset + 1), Token::FatArrow);
+                    }
+                }
+                Token::Eof => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+
+    /// Parse the `for`/`if` clauses of a comprehension, starting at `for`.
+    fn parse_comp_clauses(&mut self) -> Result<Vec<CompClause>> {
+        let mut clauses = Vec::new();
+        loop {
+            self.skip_newlines();
+            match self.peek() {
+                Token::KwFor => {
+                    self.advance();
+                    let mut vars = Vec::new();
+                    loop {
+                        match self.advance().node.clone() {
+                            Token::Ident(n) => vars.push(n),
+                            other => return Err(LatchError::UnexpectedToken {
+                                expected: "loop variable".into(),
+                                found: format!("{:?}", other),
+                                line: self.line(),
+                            }),
+                        }
+                        if !matches!(self.peek(), Token::Comma) {
+                            break;
+                        }
+                        self.advance();
+                    }
+                    self.expect(&Token::KwIn)?;
+                    clauses.push(CompClause::For { vars, iter: self.parse_expr()? });
+                }
+                Token::KwIf if !clauses.is_empty() => {
+                    self.advance();
+                    clauses.push(CompClause::If(self.parse_expr()?));
+                }
+                _ => break,
+            }
+        }
+        if clauses.is_empty() {
+            self.expect(&Token::KwFor)?;
+        }
+        self.skip_newlines();
+        Ok(clauses)
+    }
+
+    /// Finish `{key: value for ...}` once the first entry has been read.
+    fn finish_dict_comp(&mut self, key: Expr, value: Expr) -> Result<Expr> {
+        let clauses = self.parse_comp_clauses()?;
+        self.expect(&Token::RBrace)?;
+        Ok(Expr::DictComp { key: Box::new(key), value: Box::new(value), clauses })
+    }
+
     /// Check if the current position is a list comprehension by scanning for 'for' keyword
     fn scan_for_list_comprehension(&self) -> bool {
         let mut depth = 1; // bracket depth
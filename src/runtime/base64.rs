@@ -1,22 +1,21 @@
 use crate::env::Value;
 use crate::error::{LatchError, Result};
+use crate::runtime::bytes;
 
 pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
     match method {
         "encode" => {
             let data = args.first()
                 .ok_or_else(|| LatchError::ArgCountMismatch { name: "base64.encode".into(), expected: 1, found: 0 })?
-                .as_str()?;
-            let encoded = base64::encode(data.as_bytes());
-            Ok(Value::Str(encoded))
+                .as_bytes()?;
+            Ok(Value::Str(bytes::to_base64(data)))
         }
 
         "decode" => {
             let data = args.first()
                 .ok_or_else(|| LatchError::ArgCountMismatch { name: "base64.decode".into(), expected: 1, found: 0 })?
                 .as_str()?;
-            let decoded = base64::decode(data)
-                .map_err(|e| LatchError::GenericError(format!("Base64 decode error: {}", e)))?;
+            let decoded = bytes::from_base64(data)?;
             Ok(Value::Str(String::from_utf8_lossy(&decoded).to_string()))
         }
 
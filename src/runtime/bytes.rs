@@ -0,0 +1,170 @@
+use base64::Engine;
+
+use crate::env::Value;
+use crate::error::{LatchError, Result};
+
+pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
+    match method {
+        // bytes.encode(str, encoding="utf-8") — text → bytes
+        "encode" => {
+            let text = arg(&args, 0, "bytes.encode")?.as_str()?;
+            let encoding = encoding_arg(&args, 1)?;
+            Ok(Value::new_bytes(encode(text, encoding)?))
+        }
+
+        // bytes.decode(b, encoding="utf-8") — bytes → text; invalid input is an error
+        // unless the encoding is "utf-8-lossy"
+        "decode" => {
+            let data = arg(&args, 0, "bytes.decode")?.as_bytes()?;
+            let encoding = encoding_arg(&args, 1)?;
+            Ok(Value::Str(decode(data, encoding)?))
+        }
+
+        "from_hex" => {
+            let text = arg(&args, 0, "bytes.from_hex")?.as_str()?;
+            Ok(Value::new_bytes(from_hex(text)?))
+        }
+
+        "to_hex" => {
+            let data = arg(&args, 0, "bytes.to_hex")?.as_bytes()?;
+            Ok(Value::Str(data.iter().map(|b| format!("{b:02x}")).collect()))
+        }
+
+        "from_base64" => {
+            let text = arg(&args, 0, "bytes.from_base64")?.as_str()?;
+            Ok(Value::new_bytes(from_base64(text)?))
+        }
+
+        "to_base64" => {
+            let data = arg(&args, 0, "bytes.to_base64")?.as_bytes()?;
+            Ok(Value::Str(to_base64(data)))
+        }
+
+        // bytes.from_list([137, 80, 78, 71]) — ints 0..=255
+        "from_list" => {
+            let items = arg(&args, 0, "bytes.from_list")?.as_list()?;
+            Ok(Value::new_bytes(from_ints(&items)?))
+        }
+
+        "to_list" => {
+            let data = arg(&args, 0, "bytes.to_list")?.as_bytes()?;
+            Ok(Value::new_list(data.iter().map(|&b| Value::Int(b as i64)).collect()))
+        }
+
+        _ => Err(LatchError::UnknownMethod { module: "bytes".into(), method: method.into() }),
+    }
+}
+
+/// The `bytes(x, encoding?)` builtin: from a string, a list of ints, or bytes.
+pub fn from_value(val: &Value, encoding: Option<&Value>) -> Result<Value> {
+    match val {
+        Value::Bytes(_) => Ok(val.clone()),
+        Value::Str(s) => {
+            let encoding = match encoding {
+                Some(e) => e.as_str()?,
+                None => "utf-8",
+            };
+            Ok(Value::new_bytes(encode(s, encoding)?))
+        }
+        Value::List(_) => Ok(Value::new_bytes(from_ints(&val.as_list()?)?)),
+        other => Err(LatchError::TypeMismatch {
+            expected: "string, list, or bytes".into(),
+            found: other.type_name().into(),
+        }),
+    }
+}
+
+pub fn to_base64(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD.encode(data)
+}
+
+pub fn from_base64(text: &str) -> Result<Vec<u8>> {
+    base64::engine::general_purpose::STANDARD
+        .decode(text.trim())
+        .map_err(|e| LatchError::ValueError(format!("invalid base64: {e}")))
+}
+
+pub fn encode(text: &str, encoding: &str) -> Result<Vec<u8>> {
+    match normalize(encoding).as_str() {
+        "utf8" | "utf8lossy" => Ok(text.as_bytes().to_vec()),
+        "ascii" => match text.chars().find(|c| !c.is_ascii()) {
+            Some(c) => Err(LatchError::ValueError(format!("cannot encode {c:?} as ascii"))),
+            None => Ok(text.as_bytes().to_vec()),
+        },
+        "latin1" | "iso88591" => text.chars()
+            .map(|c| u8::try_from(c as u32)
+                .map_err(|_| LatchError::ValueError(format!("cannot encode {c:?} as latin-1"))))
+            .collect(),
+        "utf16le" => Ok(text.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()),
+        "utf16be" => Ok(text.encode_utf16().flat_map(|u| u.to_be_bytes()).collect()),
+        _ => Err(unknown_encoding(encoding)),
+    }
+}
+
+pub fn decode(data: &[u8], encoding: &str) -> Result<String> {
+    match normalize(encoding).as_str() {
+        "utf8" => String::from_utf8(data.to_vec())
+            .map_err(|e| LatchError::ValueError(format!("invalid utf-8: {e}"))),
+        "utf8lossy" => Ok(String::from_utf8_lossy(data).into_owned()),
+        "ascii" => match data.iter().position(|b| !b.is_ascii()) {
+            Some(i) => Err(LatchError::ValueError(format!("invalid ascii byte 0x{:02x} at offset {i}", data[i]))),
+            None => Ok(data.iter().map(|&b| b as char).collect()),
+        },
+        "latin1" | "iso88591" => Ok(data.iter().map(|&b| b as char).collect()),
+        "utf16le" | "utf16be" => {
+            if !data.len().is_multiple_of(2) {
+                return Err(LatchError::ValueError("utf-16 data has an odd number of bytes".into()));
+            }
+            let le = normalize(encoding) == "utf16le";
+            let units: Vec<u16> = data.chunks_exact(2)
+                .map(|c| if le { u16::from_le_bytes([c[0], c[1]]) } else { u16::from_be_bytes([c[0], c[1]]) })
+                .collect();
+            String::from_utf16(&units).map_err(|e| LatchError::ValueError(format!("invalid utf-16: {e}")))
+        }
+        _ => Err(unknown_encoding(encoding)),
+    }
+}
+
+fn from_hex(text: &str) -> Result<Vec<u8>> {
+    let digits: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if !digits.len().is_multiple_of(2) {
+        return Err(LatchError::ValueError("hex string has an odd number of digits".into()));
+    }
+    digits.chunks(2)
+        .map(|pair| {
+            let s: String = pair.iter().collect();
+            u8::from_str_radix(&s, 16).map_err(|_| LatchError::ValueError(format!("invalid hex byte '{s}'")))
+        })
+        .collect()
+}
+
+fn from_ints(items: &[Value]) -> Result<Vec<u8>> {
+    items.iter()
+        .map(|v| {
+            let n = v.as_int()?;
+            u8::try_from(n).map_err(|_| LatchError::ValueError(format!("byte value {n} out of range 0..255")))
+        })
+        .collect()
+}
+
+/// "UTF-8", "utf_8" and "utf8" all name the same encoding.
+fn normalize(encoding: &str) -> String {
+    encoding.to_ascii_lowercase().replace(['-', '_'], "")
+}
+
+fn unknown_encoding(encoding: &str) -> LatchError {
+    LatchError::ValueError(format!(
+        "unknown encoding '{encoding}' (expected utf-8, utf-8-lossy, ascii, latin-1, utf-16le or utf-16be)"
+    ))
+}
+
+fn arg<'a>(args: &'a [Value], i: usize, name: &str) -> Result<&'a Value> {
+    args.get(i).ok_or_else(|| LatchError::ArgCountMismatch { name: name.into(), expected: i + 1, found: args.len() })
+}
+
+fn encoding_arg(args: &[Value], i: usize) -> Result<&str> {
+    match args.get(i) {
+        Some(v) => v.as_str(),
+        None => Ok("utf-8"),
+    }
+}
@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::sync::Arc;
 
-use crate::env::Value;
+use crate::env::{Resource, ResourceKind, Value};
 use crate::error::{LatchError, Result};
 
 pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
@@ -44,10 +44,32 @@ pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
         }
 
         "list" => {
-            let map: HashMap<String, Value> = std::env::vars()
-                .map(|(k, v)| (k, Value::Str(v)))
-                .collect();
-            Ok(Value::new_map(map))
+            let mut vars: Vec<(String, String)> = std::env::vars().collect();
+            vars.sort();
+            Ok(Value::new_map(vars.into_iter().map(|(k, v)| (k.into(), Value::Str(v))).collect()))
+        }
+
+        // env.scoped({"KEY": "val", "OTHER": null}) — set/unset variables until
+        // the returned resource is released (end of a `with` block)
+        "scoped" => {
+            let vars = match args.first() {
+                Some(Value::Map(m)) => m.lock().unwrap().clone(),
+                other => return Err(LatchError::TypeMismatch {
+                    expected: "dict".into(),
+                    found: other.map(|v| v.type_name()).unwrap_or("none").into(),
+                }),
+            };
+            let mut saved = Vec::new();
+            for (key, val) in vars {
+                let key = key.to_string();
+                saved.push((key.clone(), std::env::var(&key).ok()));
+                // SAFETY: same as env.set() above.
+                match val {
+                    Value::Null => unsafe { std::env::remove_var(&key) },
+                    other => unsafe { std::env::set_var(&key, other.to_string()) },
+                }
+            }
+            Ok(Value::Resource(Arc::new(Resource::new(ResourceKind::EnvVars(saved)))))
         }
 
         _ => Err(LatchError::UnknownMethod {
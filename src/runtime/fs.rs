@@ -1,4 +1,7 @@
-use crate::env::Value;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::env::{Dict, Resource, ResourceKind, Value};
 use crate::error::{LatchError, Result};
 
 pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
@@ -23,6 +26,26 @@ pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
             Ok(Value::Bool(true))
         }
 
+        "read_bytes" => {
+            let path = args.first()
+                .ok_or_else(|| LatchError::ArgCountMismatch { name: "fs.read_bytes".into(), expected: 1, found: 0 })?
+                .as_str()?;
+            let content = std::fs::read(path)
+                .map_err(|e| LatchError::IoError(format!("fs.read_bytes(\"{}\"): {}", path, e)))?;
+            Ok(Value::new_bytes(content))
+        }
+
+        "write_bytes" => {
+            if args.len() < 2 {
+                return Err(LatchError::ArgCountMismatch { name: "fs.write_bytes".into(), expected: 2, found: args.len() });
+            }
+            let path = args[0].as_str()?;
+            let data = args[1].as_bytes()?;
+            std::fs::write(path, data)
+                .map_err(|e| LatchError::IoError(format!("fs.write_bytes(\"{}\"): {}", path, e)))?;
+            Ok(Value::Bool(true))
+        }
+
         "exists" => {
             let path = args.first()
                 .ok_or_else(|| LatchError::ArgCountMismatch { name: "fs.exists".into(), expected: 1, found: 0 })?
@@ -137,11 +160,15 @@ pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
                 .as_str()?;
             let meta = std::fs::metadata(path)
                 .map_err(|e| LatchError::IoError(format!("fs.stat(\"{}\"): {}", path, e)))?;
-            let mut map = std::collections::HashMap::new();
-            map.insert("size".to_string(), Value::Int(meta.len() as i64));
-            map.insert("is_file".to_string(), Value::Bool(meta.is_file()));
-            map.insert("is_dir".to_string(), Value::Bool(meta.is_dir()));
-            map.insert("readonly".to_string(), Value::Bool(meta.permissions().readonly()));
+            let mut map = Dict::new();
+            map.insert("size".into(), Value::Int(meta.len() as i64));
+            map.insert("is_file".into(), Value::Bool(meta.is_file()));
+            map.insert("is_dir".into(), Value::Bool(meta.is_dir()));
+            map.insert("readonly".into(), Value::Bool(meta.permissions().readonly()));
+            if let Ok(modified) = meta.modified() {
+                let modified: chrono::DateTime<chrono::Utc> = modified.into();
+                map.insert("modified".into(), Value::DateTime(modified.fixed_offset()));
+            }
             Ok(Value::new_map(map))
         }
 
@@ -188,10 +215,8 @@ pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
             let entries = std::fs::read_dir(path)
                 .map_err(|e| LatchError::IoError(format!("fs.listdir(\"{}\"): {}", path, e)))?;
             let mut list = Vec::new();
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    list.push(Value::Str(entry.path().display().to_string()));
-                }
+            for entry in entries.flatten() {
+                list.push(Value::Str(entry.path().display().to_string()));
             }
             Ok(Value::new_list(list))
         }
@@ -203,13 +228,11 @@ pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
             let mut result = Vec::new();
             fn walk_dir(dir: &str, result: &mut Vec<Value>) -> Result<()> {
                 for entry in std::fs::read_dir(dir)
-                    .map_err(|e| LatchError::IoError(format!("fs.walk(\"{}\"): {}", dir, e)))? {
-                    if let Ok(entry) = entry {
-                        let path = entry.path();
-                        result.push(Value::Str(path.display().to_string()));
-                        if path.is_dir() {
-                            walk_dir(&path.display().to_string(), result)?;
-                        }
+                    .map_err(|e| LatchError::IoError(format!("fs.walk(\"{}\"): {}", dir, e)))?.flatten() {
+                    let path = entry.path();
+                    result.push(Value::Str(path.display().to_string()));
+                    if path.is_dir() {
+                        walk_dir(&path.display().to_string(), result)?;
                     }
                 }
                 Ok(())
@@ -218,6 +241,25 @@ pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
             Ok(Value::new_list(result))
         }
 
+        // fs.tempdir(prefix?) — fresh directory, removed at the end of a `with` block
+        "tempdir" => {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let prefix = match args.first() {
+                Some(v) => v.as_str()?.to_string(),
+                None => "latch".to_string(),
+            };
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.subsec_nanos())
+                .unwrap_or(0);
+            let dir = std::env::temp_dir().join(format!(
+                "{prefix}-{}-{nanos}-{}", std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| LatchError::IoError(format!("fs.tempdir(): {}: {}", dir.display(), e)))?;
+            Ok(Value::Resource(Arc::new(Resource::new(ResourceKind::TempDir(dir)))))
+        }
+
         _ => Err(LatchError::UnknownMethod { module: "fs".into(), method: method.into() }),
     }
 }
@@ -16,8 +16,9 @@ pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
             let headers: std::collections::HashMap<String, String> = response.headers().iter()
                 .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
                 .collect();
-            let body = response.text()
-                .map_err(|e| LatchError::HttpError(format!("http.get response: {e}")))?;
+            let body = response.bytes()
+                .map_err(|e| LatchError::HttpError(format!("http.get response: {e}")))?
+                .to_vec();
 
             Ok(Value::HttpResponse { status, body, headers })
         }
@@ -27,11 +28,15 @@ pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
                 return Err(LatchError::ArgCountMismatch { name: "http.post".into(), expected: 2, found: args.len() });
             }
             let url = args[0].as_str()?.to_string();
-            let data = args[1].as_str()?.to_string();
+            // Bytes bodies are sent as-is, as application/octet-stream
+            let (data, content_type) = match &args[1] {
+                Value::Bytes(b) => (b.to_vec(), "application/octet-stream"),
+                other => (other.as_str()?.as_bytes().to_vec(), "application/json"),
+            };
 
             let client = reqwest::blocking::Client::new();
             let response = client.post(&url)
-                .header("Content-Type", "application/json")
+                .header("Content-Type", content_type)
                 .body(data)
                 .send()
                 .map_err(|e| LatchError::HttpError(format!("http.post(\"{url}\"): {e}")))?;
@@ -40,8 +45,9 @@ pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
             let headers: std::collections::HashMap<String, String> = response.headers().iter()
                 .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
                 .collect();
-            let body = response.text()
-                .map_err(|e| LatchError::HttpError(format!("http.post response: {e}")))?;
+            let body = response.bytes()
+                .map_err(|e| LatchError::HttpError(format!("http.post response: {e}")))?
+                .to_vec();
 
             Ok(Value::HttpResponse { status, body, headers })
         }
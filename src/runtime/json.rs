@@ -1,6 +1,4 @@
-use std::collections::HashMap;
-
-use crate::env::Value;
+use crate::env::{Dict, HashKey, Value};
 use crate::error::{LatchError, Result};
 
 pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
@@ -22,7 +20,7 @@ pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
                 .ok_or_else(|| LatchError::ArgCountMismatch {
                     name: "json.stringify".into(), expected: 1, found: 0,
                 })?;
-            let json_val = latch_to_json(val);
+            let json_val = latch_to_json(val)?;
             let s = serde_json::to_string_pretty(&json_val)
                 .map_err(|e| LatchError::GenericError(format!("json.stringify: {e}")))?;
             Ok(Value::Str(s))
@@ -53,17 +51,19 @@ fn json_to_latch(val: serde_json::Value) -> Value {
             Value::new_list(arr.into_iter().map(json_to_latch).collect())
         }
         serde_json::Value::Object(obj) => {
-            let map: HashMap<String, Value> = obj.into_iter()
-                .map(|(k, v)| (k, json_to_latch(v)))
+            let map: Dict = obj.into_iter()
+                .map(|(k, v)| (k.into(), json_to_latch(v)))
                 .collect();
             Value::new_map(map)
         }
     }
 }
 
-/// Convert a Latch Value into a serde_json::Value.
-fn latch_to_json(val: &Value) -> serde_json::Value {
-    match val {
+/// Convert a Latch Value into a serde_json::Value. Int, bool and null dict
+/// keys become their text form, as do payload-free enum variants; other
+/// non-string keys are an error.
+fn latch_to_json(val: &Value) -> Result<serde_json::Value> {
+    Ok(match val {
         Value::Null => serde_json::Value::Null,
         Value::Bool(b) => serde_json::Value::Bool(*b),
         Value::Int(n) => serde_json::json!(*n),
@@ -71,29 +71,87 @@ fn latch_to_json(val: &Value) -> serde_json::Value {
         Value::Str(s) => serde_json::Value::String(s.clone()),
         Value::List(items) => {
             let guard = items.lock().unwrap();
-            serde_json::Value::Array(guard.iter().map(latch_to_json).collect())
+            serde_json::Value::Array(guard.iter().map(latch_to_json).collect::<Result<_>>()?)
         }
         Value::Map(map) => {
             let guard = map.lock().unwrap();
-            let obj: serde_json::Map<String, serde_json::Value> = guard.iter()
-                .map(|(k, v)| (k.clone(), latch_to_json(v)))
-                .collect();
+            let mut obj = serde_json::Map::new();
+            for (k, v) in guard.iter() {
+                let key = match k {
+                    HashKey::Str(s) => s.clone(),
+                    HashKey::Int(_) | HashKey::Bool(_) | HashKey::Null => k.to_string(),
+                    HashKey::Enum(def, variant, payload) if payload.is_empty() => def.variants[*variant].0.clone(),
+                    HashKey::Bytes(_) | HashKey::Tuple(_) | HashKey::Enum(..) => return Err(LatchError::TypeError(format!(
+                        "json.stringify: dict key {k} can't be a JSON object key (use a string, int, bool or null)"
+                    ))),
+                };
+                if obj.contains_key(&key) {
+                    return Err(LatchError::TypeError(format!(
+                        "json.stringify: two dict keys both become JSON key \"{key}\""
+                    )));
+                }
+                obj.insert(key, latch_to_json(v)?);
+            }
             serde_json::Value::Object(obj)
         }
+        Value::Set(set) => {
+            let guard = set.lock().unwrap();
+            serde_json::Value::Array(guard.iter().map(|k| latch_to_json(&k.to_value())).collect::<Result<_>>()?)
+        }
+        // `Env.Dev` → "Dev"; `Outcome.Failed(2, "x")` → {"Failed": {"code": 2, "msg": "x"}}
+        Value::Enum(e) if e.payload.is_empty() => serde_json::Value::String(e.variant_name().to_string()),
+        Value::Enum(e) => {
+            let mut fields = serde_json::Map::new();
+            for (name, v) in e.def.variants[e.variant].1.iter().zip(&e.payload) {
+                fields.insert(name.clone(), latch_to_json(v)?);
+            }
+            serde_json::json!({ e.variant_name(): fields })
+        }
+        Value::EnumType(_) | Value::Class(_) | Value::Interface(_) => serde_json::Value::String(val.to_string()),
+        // Objects serialize their fields
+        Value::Instance(obj) => {
+            let mut fields = serde_json::Map::new();
+            for (name, v) in obj.fields.lock().unwrap().iter() {
+                fields.insert(name.clone(), latch_to_json(v)?);
+            }
+            serde_json::Value::Object(fields)
+        }
         Value::Fn { .. } => serde_json::Value::String("<fn>".into()),
-        Value::ProcessResult { stdout, stderr, code } => {
+        Value::Task(_) => serde_json::Value::String("<task>".into()),
+        Value::Process(h) => serde_json::Value::String(h.to_string()),
+        Value::Resource(_) => serde_json::Value::String(val.to_string()),
+        Value::DateTime(_) | Value::Duration(_) => serde_json::Value::String(val.to_string()),
+        // Bytes become a base64 string
+        Value::Bytes(b) => serde_json::Value::String(crate::runtime::bytes::to_base64(b)),
+        Value::ProcessResult(p) => {
             serde_json::json!({
-                "stdout": stdout,
-                "stderr": stderr,
-                "code": code,
+                "stdout": p.stdout_text(),
+                "stderr": p.stderr_text(),
+                "code": p.code,
+                "timed_out": p.timed_out,
+                "pipestatus": p.pipestatus,
+                "stage_stderr": p.stage_stderr_text(),
+                "usage": {
+                    "wall": p.usage.wall.as_secs_f64(),
+                    "user": p.usage.user.as_secs_f64(),
+                    "sys": p.usage.sys.as_secs_f64(),
+                    "max_rss": p.usage.max_rss,
+                },
             })
         }
+        Value::Result(r) => {
+            let (value, error) = match r {
+                Ok(v) => (latch_to_json(v)?, serde_json::Value::Null),
+                Err(e) => (serde_json::Value::Null, latch_to_json(e)?),
+            };
+            serde_json::json!({ "ok": r.is_ok(), "value": value, "error": error })
+        }
         Value::HttpResponse { status, body, headers } => {
             serde_json::json!({
                 "status": status,
-                "body": body,
+                "body": String::from_utf8_lossy(body),
                 "headers": headers,
             })
         }
-    }
+    })
 }
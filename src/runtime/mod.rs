@@ -25,3 +25,8 @@ pub mod ai;
 pub mod json;
 pub mod env;
 pub mod path;
+pub mod set;
+pub mod bytes;
+pub mod base64;
+pub mod text;
+pub mod signal;
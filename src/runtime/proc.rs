@@ -1,145 +1,1105 @@
-use std::process::Command;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::env::Value;
+use crate::env::{Dict, HashKey, ProcessOutput, ResourceUsage, Value};
 use crate::error::{LatchError, Result};
+use crate::runtime::signal;
+
+/// How long a timed-out command gets between SIGTERM and SIGKILL.
+const DEFAULT_GRACE: Duration = Duration::from_secs(5);
+/// Exit code reported for a command stopped by its timeout, as timeout(1) does.
+const TIMEOUT_CODE: i32 = 124;
+/// How often a running command is polled for exit.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// How many lines of stderr a `ProcessFailed` error carries.
+const STDERR_TAIL_LINES: usize = 10;
+/// How long spawned processes get to stop when the script exits.
+const EXIT_GRACE: Duration = Duration::from_secs(2);
+
+/// Every process started by `proc.spawn`, so none outlive the script.
+static SPAWNED: Mutex<Vec<Arc<ProcHandle>>> = Mutex::new(Vec::new());
+
+/// Which pipe a line of output came from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+/// Receives each line of a running command's output (without the line
+/// ending) as it arrives. An error stops the command and is returned.
+pub type LineSink<'a> = &'a mut dyn FnMut(Stream, &str) -> Result<()>;
 
 pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
+    call_with_lines(method, args, None)
+}
+
+/// `call`, with `exec` and `pipe` handing output lines to `sink` while the
+/// command runs. For a pipeline that's every stage's stderr and the last
+/// stage's stdout. The result still captures everything.
+pub fn call_with_lines(method: &str, args: Vec<Value>, sink: Option<LineSink>) -> Result<Value> {
     match method {
         "exec" => {
             let arg = args.first()
                 .ok_or_else(|| LatchError::ArgCountMismatch { name: "proc.exec".into(), expected: 1, found: 0 })?;
+            let mut opts = ExecOpts::parse("proc.exec", args.get(1))?;
+            let (mut cmd, command) = build_command("proc.exec", arg)?;
+            opts.apply(&mut cmd);
 
-            // Parse options from second argument if provided
-            let opts = if args.len() > 1 {
-                match &args[1] {
-                    Value::Map(m) => m.lock().unwrap().clone(),
-                    _ => std::collections::HashMap::new(),
-                }
-            } else {
-                std::collections::HashMap::new()
-            };
+            let deadline = opts.timeout.map(|t| deadline_after("proc.exec", t)).transpose()?;
+            let input = std::mem::replace(&mut opts.input, Input::Null);
+            let output = run("proc.exec", vec![cmd], input, deadline, opts.grace, false, sink)?;
+            opts.check(command, output)
+        }
 
-            let _cwd = opts.get("cwd").and_then(|v| v.as_str().ok());
-            let _timeout_secs = opts.get("timeout").and_then(|v| v.as_int().ok());
+        "pipe" => {
+            let stages = args.first()
+                .ok_or_else(|| LatchError::ArgCountMismatch { name: "proc.pipe".into(), expected: 1, found: 0 })?
+                .as_list()?;
+            if stages.is_empty() {
+                return Err(LatchError::GenericError("proc.pipe: empty pipeline".into()));
+            }
+            let mut opts = ExecOpts::parse("proc.pipe", args.get(1))?;
+            let mut commands = Vec::with_capacity(stages.len());
+            let cmds = stages.iter()
+                .map(|stage| {
+                    let (mut cmd, command) = build_command("proc.pipe", stage)?;
+                    opts.apply(&mut cmd);
+                    commands.push(command);
+                    Ok(cmd)
+                })
+                .collect::<Result<Vec<_>>>()?;
+
+            // One deadline for the whole pipeline
+            let deadline = opts.timeout.map(|t| deadline_after("proc.pipe", t)).transpose()?;
+            let input = std::mem::replace(&mut opts.input, Input::Null);
+            let output = run("proc.pipe", cmds, input, deadline, opts.grace, opts.pipefail, sink)?;
+            opts.check(commands.join(" | "), output)
+        }
+
+        // proc.spawn(cmd, opts?) — start a command in the background
+        "spawn" => {
+            let arg = args.first()
+                .ok_or_else(|| LatchError::ArgCountMismatch { name: "proc.spawn".into(), expected: 1, found: 0 })?;
+            let opts = ExecOpts::parse("proc.spawn", args.get(1))?;
+            let (mut cmd, command) = build_command("proc.spawn", arg)?;
+            opts.apply(&mut cmd);
+            Ok(Value::Process(ProcHandle::spawn(cmd, command)?))
+        }
 
-            let mut cmd = match arg {
-                // Array form: proc.exec(["git", "status"]) — no shell, direct exec
+        // proc.quote(s) — one shell word; a list gives one word per item
+        "quote" => {
+            let arg = args.first()
+                .ok_or_else(|| LatchError::ArgCountMismatch { name: "proc.quote".into(), expected: 1, found: 0 })?;
+            match arg {
                 Value::List(items) => {
-                    let items = items.lock().unwrap();
-                    if items.is_empty() {
-                        return Err(LatchError::GenericError("proc.exec: empty command list".into()));
+                    let words = items.lock().unwrap().iter()
+                        .map(|v| v.as_str().map(quote))
+                        .collect::<Result<Vec<_>>>()?;
+                    Ok(Value::Str(words.join(" ")))
+                }
+                other => Ok(Value::Str(quote(other.as_str()?))),
+            }
+        }
+
+        // proc.split(s) — the words a POSIX shell would see, without expansion
+        "split" => {
+            let text = args.first()
+                .ok_or_else(|| LatchError::ArgCountMismatch { name: "proc.split".into(), expected: 1, found: 0 })?
+                .as_str()?;
+            let words = split(text)?;
+            Ok(Value::new_list(words.into_iter().map(Value::Str).collect()))
+        }
+
+        _ => Err(LatchError::UnknownMethod { module: "proc".into(), method: method.into() }),
+    }
+}
+
+// ── Shell quoting ────────────────────────────────────────────
+
+/// Quote `s` as a single POSIX shell word: left alone when it's made only of
+/// characters no shell treats specially, otherwise single-quoted.
+pub fn quote(s: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "_-./:=@%+,".contains(c);
+    if !s.is_empty() && s.chars().all(plain) {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// Split `s` into words following POSIX shell quoting: whitespace separates
+/// words, single quotes keep everything literal, double quotes keep all but
+/// `\` escapes of `$`, `` ` ``, `"`, `\` and newline, and a backslash outside
+/// quotes escapes the next character. No variable or glob expansion.
+pub fn split(s: &str) -> Result<Vec<String>> {
+    let unterminated = |what: &str| LatchError::ValueError(format!("proc.split: unterminated {what} in: {s}"));
+    let mut words = Vec::new();
+    let mut word = String::new();
+    // A word has started, even if it's empty so far (`''`)
+    let mut in_word = false;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated("single quote")),
                     }
-                    let cmd_parts: Vec<String> = items.iter()
-                        .map(|v| match v {
-                            Value::Str(s) => Ok(s.clone()),
-                            other => Err(LatchError::TypeMismatch {
-                                expected: "string".into(),
-                                found: other.type_name().into(),
-                            }),
-                        })
-                        .collect::<Result<_>>()?;
-                    let mut c = Command::new(&cmd_parts[0]);
-                    c.args(&cmd_parts[1..]);
-                    c
-                }
-                // String form: proc.exec("ls -la") — via shell
-                Value::Str(cmd_str) => {
-                    if cfg!(target_os = "windows") {
-                        let mut c = Command::new("cmd");
-                        c.args(["/C", cmd_str]);
-                        c
-                    } else {
-                        let mut c = Command::new("sh");
-                        c.args(["-c", cmd_str]);
-                        c
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('\n') => {}
+                            Some(c @ ('$' | '`' | '"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err(unterminated("double quote")),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(unterminated("double quote")),
                     }
                 }
-                _ => return Err(LatchError::TypeMismatch {
-                    expected: "string or list".into(),
-                    found: arg.type_name().into(),
-                }),
+            }
+            '\\' => match chars.next() {
+                // Line continuation
+                Some('\n') => {}
+                Some(c) => {
+                    in_word = true;
+                    word.push(c);
+                }
+                None => return Err(unterminated("escape")),
+            },
+            c => {
+                in_word = true;
+                word.push(c);
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// The command for a `proc.*` call and its text: a string runs through the
+/// shell, a list runs directly (`["git", "status"]`, no shell injection).
+fn build_command(name: &str, arg: &Value) -> Result<(Command, String)> {
+    match arg {
+        Value::List(items) => {
+            let items = items.lock().unwrap();
+            if items.is_empty() {
+                return Err(LatchError::GenericError(format!("{name}: empty command list")));
+            }
+            let cmd_parts: Vec<String> = items.iter()
+                .map(|v| match v {
+                    Value::Str(s) => Ok(s.clone()),
+                    other => Err(LatchError::TypeMismatch {
+                        expected: "string".into(),
+                        found: other.type_name().into(),
+                    }),
+                })
+                .collect::<Result<_>>()?;
+            let mut c = Command::new(&cmd_parts[0]);
+            c.args(&cmd_parts[1..]);
+            Ok((c, cmd_parts.join(" ")))
+        }
+        Value::Str(cmd_str) => Ok((shell_command(cmd_str), cmd_str.clone())),
+        _ => Err(LatchError::TypeMismatch {
+            expected: "string or list".into(),
+            found: arg.type_name().into(),
+        }),
+    }
+}
+
+/// Options shared by `proc.exec` and `proc.pipe`:
+/// `{cwd, env, timeout, kill_after}`.
+struct ExecOpts {
+    cwd: Option<String>,
+    env: Vec<(String, String)>,
+    /// Start from an empty environment rather than the script's
+    env_clear: bool,
+    /// Process settings applied in the child before it runs the command
+    isolation: Isolation,
+    /// Stop the command (its whole process group) after this long
+    timeout: Option<Duration>,
+    /// Time between SIGTERM and SIGKILL once the timeout fires
+    grace: Duration,
+    /// What the command reads on stdin
+    input: Input,
+    /// A pipeline fails if any stage does, not just the last
+    pipefail: bool,
+    /// Raise `ProcessFailed` instead of returning a non-zero result
+    check: bool,
+}
+
+/// Settings the child applies to itself between fork and exec.
+#[derive(Default)]
+struct Isolation {
+    umask: Option<u32>,
+    nice: Option<i32>,
+    /// `(resource, limit)` pairs for setrlimit
+    limits: Vec<(Limit, u64)>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+}
+
+/// A resource `limits` can cap.
+#[derive(Clone, Copy)]
+enum Limit {
+    /// CPU time, in seconds
+    Cpu,
+    /// Address space, in bytes
+    Memory,
+    /// Open file descriptors
+    Files,
+}
+
+impl Isolation {
+    fn parse(name: &str, get: impl Fn(&str) -> Option<Value>) -> Result<Self> {
+        let umask = match get("umask") {
+            // Given as a string it's octal, like the shell's `umask 022`
+            Some(Value::Str(s)) => Some(
+                u32::from_str_radix(s.trim_start_matches("0o"), 8)
+                    .map_err(|_| LatchError::ValueError(format!("{name}: umask \"{s}\" is not an octal mode")))?,
+            ),
+            Some(v) => Some(u32::try_from(v.as_int()?).unwrap_or(u32::MAX)),
+            None => None,
+        };
+        if umask.is_some_and(|m| m > 0o777) {
+            return Err(LatchError::ValueError(format!("{name}: umask must be between 0 and 0o777")));
+        }
+
+        let nice = get("nice").map(|v| v.as_int()).transpose()?;
+        if nice.is_some_and(|n| !(-20..=19).contains(&n)) {
+            return Err(LatchError::ValueError(format!("{name}: nice must be between -20 and 19")));
+        }
+
+        let mut limits = Vec::new();
+        if let Some(v) = get("limits") {
+            let Value::Map(map) = v else {
+                return Err(LatchError::TypeMismatch {
+                    expected: format!("{name}: limits as a dict"),
+                    found: v.type_name().into(),
+                });
             };
+            for (key, val) in map.lock().unwrap().iter() {
+                let key = key.to_string();
+                let limit = match key.as_str() {
+                    "cpu" => Limit::Cpu,
+                    "memory" => Limit::Memory,
+                    "files" => Limit::Files,
+                    other => return Err(LatchError::ValueError(format!(
+                        "{name}: unknown limit \"{other}\" (expected cpu, memory or files)"
+                    ))),
+                };
+                let amount = match (limit, val) {
+                    // Whole seconds, rounded up so a limit never comes out as 0
+                    (Limit::Cpu, Value::Duration(_) | Value::Float(_)) => {
+                        duration_opt(name, "limits.cpu", val)?.as_secs_f64().ceil() as u64
+                    }
+                    (_, v) => u64::try_from(v.as_int()?).map_err(|_| {
+                        LatchError::ValueError(format!("{name}: limits.{key} can't be negative"))
+                    })?,
+                };
+                limits.push((limit, amount));
+            }
+        }
+
+        let id = |key: &str| -> Result<Option<u32>> {
+            get(key).map(|v| {
+                u32::try_from(v.as_int()?)
+                    .map_err(|_| LatchError::ValueError(format!("{name}: {key} must be a non-negative id")))
+            }).transpose()
+        };
+        let (uid, gid) = (id("uid")?, id("gid")?);
+        #[cfg(unix)]
+        // SAFETY: geteuid() can't fail
+        if (uid.is_some() || gid.is_some()) && unsafe { libc::geteuid() } != 0 {
+            return Err(LatchError::ValueError(format!("{name}: uid and gid need the script to run as root")));
+        }
+
+        Ok(Isolation { umask, nice: nice.map(|n| n as i32), limits, uid, gid })
+    }
+
+    #[cfg(unix)]
+    fn apply(&self, cmd: &mut Command) {
+        use std::os::unix::process::CommandExt;
+        if let Some(uid) = self.uid {
+            cmd.uid(uid);
+        }
+        if let Some(gid) = self.gid {
+            cmd.gid(gid);
+        }
+        if self.umask.is_none() && self.nice.is_none() && self.limits.is_empty() {
+            return;
+        }
+        let (umask, nice, limits) = (self.umask, self.nice, self.limits.clone());
+        // SAFETY: the hook only makes async-signal-safe calls, and doesn't allocate
+        unsafe {
+            cmd.pre_exec(move || {
+                if let Some(mask) = umask {
+                    libc::umask(mask as libc::mode_t);
+                }
+                if let Some(n) = nice {
+                    if libc::setpriority(libc::PRIO_PROCESS, 0, n) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                for &(limit, amount) in &limits {
+                    let resource = match limit {
+                        Limit::Cpu => libc::RLIMIT_CPU,
+                        Limit::Memory => libc::RLIMIT_AS,
+                        Limit::Files => libc::RLIMIT_NOFILE,
+                    };
+                    let rlim = libc::rlimit { rlim_cur: amount as libc::rlim_t, rlim_max: amount as libc::rlim_t };
+                    if libc::setrlimit(resource, &rlim) != 0 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn apply(&self, _cmd: &mut Command) {}
+}
+
+/// What a command reads on stdin.
+enum Input {
+    /// Nothing: stdin is the null device
+    Null,
+    /// These bytes, written on a background thread
+    Data(Vec<u8>),
+    /// A file, connected directly
+    File(std::fs::File),
+    /// The script's own stdin, so the command can prompt the user
+    Inherit,
+}
+
+impl ExecOpts {
+    fn parse(name: &str, arg: Option<&Value>) -> Result<Self> {
+        let opts = match arg {
+            Some(Value::Map(m)) => m.lock().unwrap().clone(),
+            _ => Dict::new(),
+        };
+        let get = |key: &str| opts.get(&HashKey::from(key)).filter(|v| !matches!(v, Value::Null));
+
+        let env = match get("env") {
+            Some(Value::Map(env_map)) => env_map.lock().unwrap().iter()
+                .filter_map(|(k, v)| Some((k.to_string(), v.as_str().ok()?.to_string())))
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let mut inputs = Vec::new();
+        match get("input") {
+            Some(Value::Bytes(b)) => inputs.push(Input::Data(b.to_vec())),
+            Some(v) => inputs.push(Input::Data(v.as_str()?.as_bytes().to_vec())),
+            None => {}
+        }
+        if let Some(path) = get("input_file") {
+            let path = path.as_str()?;
+            let file = std::fs::File::open(path)
+                .map_err(|e| LatchError::IoError(format!("{name}: input_file '{path}': {e}")))?;
+            inputs.push(Input::File(file));
+        }
+        if get("inherit_stdin").map(Value::as_bool).transpose()?.unwrap_or(false) {
+            inputs.push(Input::Inherit);
+        }
+        if inputs.len() > 1 {
+            return Err(LatchError::ValueError(format!(
+                "{name}: use only one of input, input_file and inherit_stdin"
+            )));
+        }
+
+        Ok(ExecOpts {
+            input: inputs.pop().unwrap_or(Input::Null),
+            pipefail: get("pipefail").map(Value::as_bool).transpose()?.unwrap_or(false),
+            check: get("check").map(Value::as_bool).transpose()?.unwrap_or(false),
+            cwd: get("cwd").and_then(|v| v.as_str().ok()).map(str::to_string),
+            env,
+            env_clear: get("env_clear").map(Value::as_bool).transpose()?.unwrap_or(false),
+            isolation: Isolation::parse(name, |key| get(key).cloned())?,
+            timeout: get("timeout").map(|v| duration_opt(name, "timeout", v)).transpose()?,
+            grace: get("kill_after").map(|v| duration_opt(name, "kill_after", v)).transpose()?
+                .unwrap_or(DEFAULT_GRACE),
+        })
+    }
+
+    /// The result of `command`, or with `check` an error if it failed.
+    fn check(&self, command: String, output: ProcessOutput) -> Result<Value> {
+        if self.check && output.code != 0 {
+            return Err(LatchError::ProcessFailed {
+                command,
+                code: output.code,
+                stderr: stderr_tail(&output.stderr),
+            });
+        }
+        Ok(Value::ProcessResult(output))
+    }
+
+    fn apply(&self, cmd: &mut Command) {
+        if let Some(cwd) = &self.cwd {
+            cmd.current_dir(cwd);
+        }
+        if self.env_clear {
+            cmd.env_clear();
+        }
+        for (k, v) in &self.env {
+            cmd.env(k, v);
+        }
+        self.isolation.apply(cmd);
+    }
+}
+
+/// The last few lines of a command's stderr, for an error message.
+fn stderr_tail(stderr: &[u8]) -> String {
+    let text = String::from_utf8_lossy(stderr);
+    let lines: Vec<&str> = text.trim_end().lines().collect();
+    lines[lines.len().saturating_sub(STDERR_TAIL_LINES)..].join("\n")
+}
 
-            // Apply cwd if specified
-            if let Some(ref cwd) = _cwd {
-                cmd.current_dir(cwd);
+/// A length of time given as seconds (int or float) or a duration (`30s`).
+fn duration_opt(name: &str, key: &str, val: &Value) -> Result<Duration> {
+    let secs = match val {
+        Value::Duration(d) => d.num_milliseconds() as f64 / 1000.0,
+        Value::Int(n) => *n as f64,
+        Value::Float(f) => *f,
+        other => return Err(LatchError::TypeMismatch {
+            expected: format!("{name}: {key} as seconds or a duration"),
+            found: other.type_name().into(),
+        }),
+    };
+    if secs < 0.0 {
+        return Err(LatchError::ValueError(format!("{name}: {key} can't be negative")));
+    }
+    Duration::try_from_secs_f64(secs)
+        .map_err(|_| LatchError::ValueError(format!("{name}: {key} is out of range")))
+}
+
+/// The moment a `timeout` starting now runs out.
+fn deadline_after(name: &str, timeout: Duration) -> Result<Instant> {
+    Instant::now().checked_add(timeout)
+        .ok_or_else(|| LatchError::ValueError(format!("{name}: timeout is out of range")))
+}
+
+/// `sh -c cmd`, or `cmd /C cmd` on Windows.
+fn shell_command(cmd_str: &str) -> Command {
+    if cfg!(target_os = "windows") {
+        let mut c = Command::new("cmd");
+        c.args(["/C", cmd_str]);
+        c
+    } else {
+        let mut c = Command::new("sh");
+        c.args(["-c", cmd_str]);
+        c
+    }
+}
+
+/// Run `cmds` to completion as a pipeline: each stage's stdout is wired to
+/// the next stage's stdin with an OS pipe, the first reads `input` and the
+/// last one's stdout is captured, along with every stage's stderr. The stages
+/// share a process group, so when `deadline` passes everything they started
+/// is stopped and the result is marked `timed_out`. With a `sink`, each
+/// output line is also handed over as it arrives.
+fn run(
+    name: &str,
+    cmds: Vec<Command>,
+    input: Input,
+    deadline: Option<Instant>,
+    grace: Duration,
+    pipefail: bool,
+    mut sink: Option<LineSink>,
+) -> Result<ProcessOutput> {
+    let io_err = |e: std::io::Error| LatchError::IoError(format!("{name}: {e}"));
+    // A command reading the terminal has to stay in the foreground process
+    // group, or its first read stops it with SIGTTIN
+    let group = !matches!(input, Input::Inherit);
+    let (stdin, data) = match input {
+        Input::Null => (Stdio::null(), None),
+        Input::Data(data) => (Stdio::piped(), Some(data)),
+        Input::File(file) => (Stdio::from(file), None),
+        Input::Inherit => (Stdio::inherit(), None),
+    };
+
+    let started = Instant::now();
+    // Taken before spawning, so a signal arriving from here on (even before
+    // the children exist) is passed on to them
+    let signals_seen = signal::seen();
+    let stage_count = cmds.len();
+    let mut children: Vec<Child> = Vec::with_capacity(stage_count);
+    // Untracked again when this returns
+    let mut tracked = Vec::with_capacity(stage_count);
+    let mut stdin = Some(stdin);
+    for (i, mut cmd) in cmds.into_iter().enumerate() {
+        let stage_stdin = match i {
+            0 => stdin.take().unwrap_or_else(Stdio::null),
+            _ => children[i - 1].stdout.take().map(Stdio::from).unwrap_or_else(Stdio::null),
+        };
+        // An interactive command gets the terminal for its output too, so
+        // prompts without a newline show up and editors can draw
+        let (stage_stdout, stage_stderr) = match group {
+            false if i + 1 == stage_count => (Stdio::inherit(), Stdio::inherit()),
+            false => (Stdio::piped(), Stdio::inherit()),
+            true => (Stdio::piped(), Stdio::piped()),
+        };
+        cmd.stdin(stage_stdin).stdout(stage_stdout).stderr(stage_stderr);
+        #[cfg(unix)]
+        if group {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(children.first().map_or(0, |c| c.id() as i32));
+        }
+        match cmd.spawn() {
+            Ok(child) => {
+                match group {
+                    true if i > 0 => {}
+                    true => tracked.push(signal::track(-(child.id() as i32))),
+                    false => tracked.push(signal::track(child.id() as i32)),
+                }
+                children.push(child);
             }
+            Err(e) => {
+                terminate(&mut children, KILL_SIGNAL, group);
+                for child in &mut children {
+                    let _ = child.wait();
+                }
+                return Err(io_err(e));
+            }
+        }
+    }
+
+    // Feed stdin and drain the pipes on their own threads so a chatty
+    // command can't fill a pipe and stall. The channel closes once every
+    // pipe has.
+    let writer = match (data, children.first_mut().and_then(|c| c.stdin.take())) {
+        (Some(data), Some(mut stdin)) => Some(thread::spawn(move || {
+            let _ = stdin.write_all(&data);
+        })),
+        _ => None,
+    };
+    let (tx, rx) = mpsc::channel();
+    let send_lines = sink.is_some();
+    let stderrs: Vec<_> = children.iter_mut()
+        .map(|c| drain(c.stderr.take(), Stream::Stderr, tx.clone(), send_lines))
+        .collect();
+    let stdout = drain(children.last_mut().and_then(|c| c.stdout.take()), Stream::Stdout, tx, send_lines);
 
-            // Apply env vars if specified
-            if let Some(Value::Map(env_map)) = opts.get("env") {
-                let env_vars = env_map.lock().unwrap();
-                for (k, v) in env_vars.iter() {
-                    if let Ok(val) = v.as_str() {
-                        cmd.env(k, val);
+    let mut watchdog = Watchdog { deadline, grace, group, kill_at: None, timed_out: false, signals_seen };
+    // A signal that came in while the children were starting
+    watchdog.forward_signal(&mut children, Instant::now());
+    let mut statuses: Vec<Option<(ExitStatus, ResourceUsage)>> = vec![None; stage_count];
+    let mut pipes_open = true;
+    let outcome = loop {
+        let exited = match watchdog.poll(&mut children, &mut statuses) {
+            Ok(exited) => exited,
+            Err(e) => break Err(io_err(e)),
+        };
+        if !pipes_open && exited {
+            break Ok(());
+        }
+        if !pipes_open {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok((stream, line)) => {
+                if let Some(sink) = sink.as_mut() {
+                    if let Err(e) = sink(stream, &String::from_utf8_lossy(&line)) {
+                        break Err(e);
                     }
                 }
             }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => pipes_open = false,
+        }
+    };
+    if let Err(e) = outcome {
+        // The sink gave up: don't leave the command running
+        terminate(&mut children, KILL_SIGNAL, group);
+        for child in &mut children {
+            let _ = child.wait();
+        }
+        return Err(e);
+    }
+    drop(rx);
 
-            // Execute (timeout requires additional crate, skipping for now)
-            let output = cmd.output().map_err(|e| LatchError::IoError(format!("proc.exec: {e}")))?;
+    // Stopped by Ctrl-C and nothing traps it: unwind rather than carry on
+    signal::check()?;
 
-            let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            let code = output.status.code().unwrap_or(-1);
+    let stdout = stdout.join().unwrap_or_default();
+    let mut stage_stderr: Vec<Vec<u8>> = stderrs.into_iter().map(|h| h.join().unwrap_or_default()).collect();
+    if let Some(writer) = writer {
+        let _ = writer.join();
+    }
+    let timed_out = watchdog.timed_out;
+    let pipestatus: Vec<i32> = statuses.iter()
+        .map(|s| s.and_then(|(s, _)| s.code()).unwrap_or(-1))
+        .collect();
+    let mut usage = statuses.iter().flatten()
+        .fold(ResourceUsage::default(), |total, (_, u)| ResourceUsage {
+            wall: Duration::ZERO,
+            user: total.user + u.user,
+            sys: total.sys + u.sys,
+            max_rss: total.max_rss.max(u.max_rss),
+        });
+    usage.wall = started.elapsed();
+    // Like bash: the last stage's code, or with `pipefail` the last non-zero one
+    let code = match pipestatus.last() {
+        _ if timed_out => TIMEOUT_CODE,
+        _ if pipefail => pipestatus.iter().rev().find(|&&c| c != 0).copied().unwrap_or(0),
+        Some(&code) => code,
+        None => 0,
+    };
+    let (stderr, stage_stderr) = match stage_stderr.len() {
+        1 => (stage_stderr.pop().unwrap_or_default(), Vec::new()),
+        _ => (stage_stderr.concat(), stage_stderr),
+    };
+    Ok(ProcessOutput { stdout, stderr, code, timed_out, pipestatus, stage_stderr, usage: Box::new(usage) })
+}
 
-            Ok(Value::ProcessResult { stdout, stderr, code })
+/// Read a pipe to the end, returning everything read. With `send_lines`,
+/// each line is also sent on `tx` as soon as it's complete.
+fn drain<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    stream: Stream,
+    tx: Sender<(Stream, Vec<u8>)>,
+    send_lines: bool,
+) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        let Some(pipe) = pipe else { return buf };
+        let mut reader = BufReader::new(pipe);
+        loop {
+            let start = buf.len();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) if send_lines => {
+                    let mut line = &buf[start..];
+                    if let Some(rest) = line.strip_suffix(b"\n") {
+                        line = rest.strip_suffix(b"\r").unwrap_or(rest);
+                    }
+                    let _ = tx.send((stream, line.to_vec()));
+                }
+                Ok(_) => {}
+            }
         }
+        buf
+    })
+}
 
-        "pipe" => {
-            let cmds = args.first()
-                .ok_or_else(|| LatchError::ArgCountMismatch { name: "proc.pipe".into(), expected: 1, found: 0 })?
-                .as_list()?;
+/// Enforces a command's timeout: once `deadline` passes its process group
+/// gets SIGTERM, then SIGKILL when `grace` runs out or the command exits,
+/// whichever is first, so nothing it started outlives it. A signal sent to
+/// the script (Ctrl-C) that no trap handles is passed on to the group the
+/// same way.
+struct Watchdog {
+    deadline: Option<Instant>,
+    grace: Duration,
+    /// Whether the children share their own process group
+    group: bool,
+    kill_at: Option<Instant>,
+    timed_out: bool,
+    /// `signal::received()` when last checked
+    signals_seen: usize,
+}
 
-            let mut input = String::new();
-
-            for cmd_val in &cmds {
-                let cmd_str = cmd_val.as_str()?.to_string();
-
-                let mut child = if cfg!(target_os = "windows") {
-                    Command::new("cmd").args(["/C", &cmd_str])
-                        .stdin(std::process::Stdio::piped())
-                        .stdout(std::process::Stdio::piped())
-                        .stderr(std::process::Stdio::piped())
-                        .spawn()
-                        .map_err(|e| LatchError::IoError(format!("proc.pipe: {e}")))?
-                } else {
-                    Command::new("sh").args(["-c", &cmd_str])
-                        .stdin(std::process::Stdio::piped())
-                        .stdout(std::process::Stdio::piped())
-                        .stderr(std::process::Stdio::piped())
-                        .spawn()
-                        .map_err(|e| LatchError::IoError(format!("proc.pipe: {e}")))?
-                };
+impl Watchdog {
+    /// Collect the exit status of any child that has exited, signalling the
+    /// rest as due. True once all of them have exited.
+    fn poll(
+        &mut self,
+        children: &mut [Child],
+        statuses: &mut [Option<(ExitStatus, ResourceUsage)>],
+    ) -> std::io::Result<bool> {
+        for (child, status) in children.iter_mut().zip(statuses.iter_mut()) {
+            if status.is_none() {
+                *status = reap(child)?;
+            }
+        }
+        let exited = statuses.iter().all(Option::is_some);
+        // Keep watching after the children exit: something they left in the
+        // background can still hold the pipes open
+        let now = Instant::now();
+        self.forward_signal(children, now);
+        if self.deadline.is_some_and(|d| now >= d) {
+            self.deadline = None;
+            self.timed_out = true;
+            self.kill_at = now.checked_add(self.grace);
+            terminate(children, TERM_SIGNAL, self.group);
+        }
+        if self.kill_at.is_some_and(|k| exited || now >= k) {
+            self.kill_at = None;
+            terminate(children, KILL_SIGNAL, self.group);
+        }
+        Ok(exited)
+    }
 
-                if !input.is_empty() {
-                    use std::io::Write;
-                    if let Some(ref mut stdin) = child.stdin {
-                        stdin.write_all(input.as_bytes())
-                            .map_err(|e| LatchError::IoError(format!("proc.pipe stdin: {e}")))?;
+    /// Pass a signal the script got since last checked on to the children,
+    /// with SIGKILL to follow if they outlast the grace period. A signal an
+    /// `on_signal` trap handles leaves them running.
+    fn forward_signal(&mut self, children: &mut [Child], now: Instant) {
+        let received = signal::received();
+        if received == self.signals_seen {
+            return;
+        }
+        self.signals_seen = received;
+        if let Some(sig) = signal::interrupted() {
+            terminate(children, sig, self.group);
+            if self.kill_at.is_none() {
+                self.kill_at = now.checked_add(self.grace);
+            }
+        }
+    }
+}
+
+/// The exit status of `child` and the resources it used, if it has exited.
+#[cfg(unix)]
+fn reap(child: &mut Child) -> std::io::Result<Option<(ExitStatus, ResourceUsage)>> {
+    use std::os::unix::process::ExitStatusExt;
+    let mut status = 0;
+    // SAFETY: rusage is plain data, and wait4 only writes through the pointers
+    let mut ru: libc::rusage = unsafe { std::mem::zeroed() };
+    let pid = unsafe { libc::wait4(child.id() as libc::pid_t, &mut status, libc::WNOHANG, &mut ru) };
+    match pid {
+        0 => Ok(None),
+        -1 => {
+            let err = std::io::Error::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::EINTR) => Ok(None),
+                // Already reaped while being signalled: no usage to report
+                Some(libc::ECHILD) => Ok(child.try_wait()?.map(|s| (s, ResourceUsage::default()))),
+                _ => Err(err),
+            }
+        }
+        _ => {
+            let time = |t: libc::timeval| Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1000);
+            // ru_maxrss is in kilobytes, except on macOS where it's bytes
+            let scale = if cfg!(target_os = "macos") { 1 } else { 1024 };
+            Ok(Some((ExitStatus::from_raw(status), ResourceUsage {
+                wall: Duration::ZERO,
+                user: time(ru.ru_utime),
+                sys: time(ru.ru_stime),
+                max_rss: ru.ru_maxrss.max(0) as u64 * scale,
+            })))
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn reap(child: &mut Child) -> std::io::Result<Option<(ExitStatus, ResourceUsage)>> {
+    Ok(child.try_wait()?.map(|s| (s, ResourceUsage::default())))
+}
+
+// ── Background processes ─────────────────────────────────────
+
+/// A process started by `proc.spawn`. It leads its own process group; its
+/// output is read line by line on background threads.
+#[derive(Debug)]
+pub struct ProcHandle {
+    pub pid: u32,
+    pub command: String,
+    child: Mutex<Child>,
+    /// Entry in the table a second Ctrl-C kills from, until it exits
+    tracked: Mutex<signal::Tracked>,
+    stdin: Mutex<Option<ChildStdin>>,
+    stdout: Mutex<Receiver<String>>,
+    stderr: Mutex<Receiver<String>>,
+}
+
+impl ProcHandle {
+    fn spawn(mut cmd: Command, command: String) -> Result<Arc<ProcHandle>> {
+        cmd.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+        let mut child = cmd.spawn().map_err(|e| LatchError::IoError(format!("proc.spawn: {e}")))?;
+        let handle = Arc::new(ProcHandle {
+            pid: child.id(),
+            command,
+            tracked: Mutex::new(signal::track(-(child.id() as i32))),
+            stdin: Mutex::new(child.stdin.take()),
+            stdout: Mutex::new(read_lines(child.stdout.take())),
+            stderr: Mutex::new(read_lines(child.stderr.take())),
+            child: Mutex::new(child),
+        });
+        let mut spawned = SPAWNED.lock().unwrap();
+        spawned.retain(|h| h.is_running());
+        spawned.push(handle.clone());
+        Ok(handle)
+    }
+
+    /// Whether the process is still running. A handle someone is blocked
+    /// waiting on counts as running.
+    pub fn is_running(&self) -> bool {
+        match self.child.try_lock() {
+            Ok(mut child) => {
+                let running = matches!(child.try_wait(), Ok(None));
+                if !running {
+                    self.tracked.lock().unwrap().release();
+                }
+                running
+            }
+            Err(_) => true,
+        }
+    }
+
+    /// The exit code once the process has finished (-1 if a signal killed it).
+    pub fn exit_code(&self) -> Option<i64> {
+        let mut child = self.child.try_lock().ok()?;
+        let status = child.try_wait().ok()??;
+        Some(status.code().unwrap_or(-1) as i64)
+    }
+
+    /// Field access: `h.pid`, `h.command`, `h.code` (null while running).
+    pub fn field(&self, name: &str) -> Option<Value> {
+        match name {
+            "pid" => Some(Value::Int(self.pid as i64)),
+            "command" => Some(Value::Str(self.command.clone())),
+            "code" => Some(self.exit_code().map(Value::Int).unwrap_or(Value::Null)),
+            _ => None,
+        }
+    }
+
+    /// Methods on a handle: `h.wait(timeout?)`, `h.kill(signal?)`, ...
+    pub fn call(&self, method: &str, args: Vec<Value>) -> Result<Value> {
+        let name = format!("proc.{method}");
+        match method {
+            // wait(timeout?) — the exit code, or null if still running after `timeout`
+            "wait" => {
+                let [timeout] = method_args(args, ["timeout"]);
+                let timeout = timeout.map(|t| duration_opt(&name, "timeout", &t)).transpose()?;
+                let mut child = self.child.lock().unwrap();
+                let deadline = timeout.map(|t| deadline_after(&name, t)).transpose()?;
+                let status = wait_until(&name, &mut child, deadline)?;
+                Ok(status.map(|s| Value::Int(s.code().unwrap_or(-1) as i64)).unwrap_or(Value::Null))
+            }
+            // kill(signal = "TERM") — signal the process group; false if it already exited
+            "kill" => {
+                let [signal] = method_args(args, ["signal"]);
+                let signal = signal::number(&name, signal.as_ref(), TERM_SIGNAL)?;
+                if !self.is_running() {
+                    return Ok(Value::Bool(false));
+                }
+                self.signal(signal);
+                Ok(Value::Bool(true))
+            }
+            "is_running" => Ok(Value::Bool(self.is_running())),
+            // write_stdin(data) — string or bytes, written immediately
+            "write_stdin" => {
+                let [data] = method_args(args, ["data"]);
+                let data = data.ok_or_else(|| LatchError::ArgCountMismatch { name: name.clone(), expected: 1, found: 0 })?;
+                let bytes = match &data {
+                    Value::Bytes(b) => b.to_vec(),
+                    other => other.as_str()?.as_bytes().to_vec(),
+                };
+                let mut stdin = self.stdin.lock().unwrap();
+                let pipe = stdin.as_mut()
+                    .ok_or_else(|| LatchError::GenericError(format!("{name}: stdin is closed")))?;
+                pipe.write_all(&bytes).and_then(|()| pipe.flush())
+                    .map_err(|e| LatchError::IoError(format!("{name}: {e}")))?;
+                Ok(Value::Null)
+            }
+            // close_stdin() — send EOF
+            "close_stdin" => {
+                self.stdin.lock().unwrap().take();
+                Ok(Value::Null)
+            }
+            // read_line(stream = "stdout", timeout?) — the next line, or null at
+            // end of output or when nothing arrives within `timeout`
+            "read_line" => {
+                let [stream, timeout] = method_args(args, ["stream", "timeout"]);
+                let rx = match stream.as_ref().map(Value::as_str).transpose()? {
+                    None | Some("stdout") => &self.stdout,
+                    Some("stderr") => &self.stderr,
+                    Some(other) => return Err(LatchError::ValueError(format!(
+                        "{name}: stream must be \"stdout\" or \"stderr\", got \"{other}\""
+                    ))),
+                };
+                let timeout = timeout.map(|t| duration_opt(&name, "timeout", &t)).transpose()?;
+                let deadline = timeout.map(|t| deadline_after(&name, t)).transpose()?;
+                let rx = rx.lock().unwrap();
+                loop {
+                    signal::check()?;
+                    match rx.recv_timeout(POLL_INTERVAL) {
+                        Ok(line) => return Ok(Value::Str(line)),
+                        Err(RecvTimeoutError::Disconnected) => return Ok(Value::Null),
+                        Err(RecvTimeoutError::Timeout) if deadline.is_some_and(|d| Instant::now() >= d) => {
+                            return Ok(Value::Null);
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
                     }
                 }
-                // Close stdin so the process can proceed
-                drop(child.stdin.take());
+            }
+            _ => Err(LatchError::UnknownMethod { module: "proc handle".into(), method: method.into() }),
+        }
+    }
+
+    #[cfg(unix)]
+    fn signal(&self, signal: i32) {
+        // SAFETY: plain syscall on the group this handle's process leads
+        unsafe {
+            libc::killpg(self.pid as libc::pid_t, signal);
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn signal(&self, _signal: i32) {
+        let _ = self.child.lock().unwrap().kill();
+    }
+}
 
-                let output = child.wait_with_output()
-                    .map_err(|e| LatchError::IoError(format!("proc.pipe: {e}")))?;
+impl std::fmt::Display for ProcHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.exit_code() {
+            Some(code) => write!(f, "<proc {} exited {code}>", self.pid),
+            None => write!(f, "<proc {} running>", self.pid),
+        }
+    }
+}
 
-                input = String::from_utf8_lossy(&output.stdout).to_string();
+/// Stop every spawned process that's still running, process group and all:
+/// SIGTERM, then SIGKILL for whatever is left after a short grace period.
+/// Called when the script exits.
+pub fn kill_spawned() {
+    let handles: Vec<_> = SPAWNED.lock().unwrap().drain(..).filter(|h| h.is_running()).collect();
+    if handles.is_empty() {
+        return;
+    }
+    for h in &handles {
+        h.signal(TERM_SIGNAL);
+    }
+    let deadline = Instant::now() + EXIT_GRACE;
+    while Instant::now() < deadline && handles.iter().any(|h| h.is_running()) {
+        thread::sleep(POLL_INTERVAL);
+    }
+    for h in handles.iter().filter(|h| h.is_running()) {
+        h.signal(KILL_SIGNAL);
+        let _ = h.child.lock().unwrap().wait();
+    }
+}
 
-                if !output.status.success() {
-                    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-                    let code = output.status.code().unwrap_or(-1);
-                    return Ok(Value::ProcessResult { stdout: input, stderr, code });
+/// Collect a pipe's lines on a background thread; the channel closes at EOF.
+fn read_lines<R: Read + Send + 'static>(pipe: Option<R>) -> Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    if let Some(pipe) = pipe {
+        thread::spawn(move || {
+            let mut reader = BufReader::new(pipe);
+            let mut buf = Vec::new();
+            while matches!(reader.read_until(b'\n', &mut buf), Ok(n) if n > 0) {
+                let line = buf.strip_suffix(b"\n").unwrap_or(&buf);
+                let line = line.strip_suffix(b"\r").unwrap_or(line);
+                if tx.send(String::from_utf8_lossy(line).into_owned()).is_err() {
+                    break;
                 }
+                buf.clear();
             }
+        });
+    }
+    rx
+}
 
-            Ok(Value::ProcessResult {
-                stdout: input,
-                stderr: String::new(),
-                code: 0,
-            })
+/// Method arguments by position, or by name from a trailing keyword dict.
+fn method_args<const N: usize>(mut args: Vec<Value>, names: [&str; N]) -> [Option<Value>; N] {
+    let kwargs = match args.last() {
+        Some(Value::Map(m)) if is_kwargs(&m.lock().unwrap(), &names) => {
+            let m = m.lock().unwrap().clone();
+            args.pop();
+            m
         }
+        _ => Dict::new(),
+    };
+    let mut positional = args.into_iter();
+    names.map(|name| {
+        positional.next()
+            .or_else(|| kwargs.get(&HashKey::from(name)).cloned())
+            .filter(|v| !matches!(v, Value::Null))
+    })
+}
 
-        _ => Err(LatchError::UnknownMethod { module: "proc".into(), method: method.into() }),
+/// A trailing dict holds keyword arguments when every key is a known name.
+fn is_kwargs(dict: &Dict, names: &[&str]) -> bool {
+    !dict.is_empty() && dict.keys().all(|k| matches!(k, HashKey::Str(s) if names.contains(&s.as_str())))
+}
+
+/// Wait for the child to exit, giving up at `deadline` or on a signal no
+/// trap handles.
+fn wait_until(name: &str, child: &mut Child, deadline: Option<Instant>) -> Result<Option<ExitStatus>> {
+    loop {
+        if let Some(status) = child.try_wait().map_err(|e| LatchError::IoError(format!("{name}: {e}")))? {
+            return Ok(Some(status));
+        }
+        signal::check()?;
+        if deadline.is_some_and(|d| Instant::now() >= d) {
+            return Ok(None);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(unix)]
+const TERM_SIGNAL: i32 = libc::SIGTERM;
+#[cfg(unix)]
+const KILL_SIGNAL: i32 = libc::SIGKILL;
+#[cfg(not(unix))]
+const TERM_SIGNAL: i32 = 15;
+#[cfg(not(unix))]
+const KILL_SIGNAL: i32 = 9;
+
+/// Send `signal` to the children's process group, or to each child when they
+/// don't have one of their own.
+#[cfg(unix)]
+fn terminate(children: &mut [Child], signal: i32, group: bool) {
+    // SAFETY: plain syscalls; the group id is the first child's pid (`process_group`)
+    unsafe {
+        match children.first() {
+            Some(leader) if group => {
+                libc::killpg(leader.id() as libc::pid_t, signal);
+            }
+            // Skip children already reaped: their pid may belong to someone else now
+            _ => for child in children.iter_mut() {
+                if matches!(child.try_wait(), Ok(None)) {
+                    libc::kill(child.id() as libc::pid_t, signal);
+                }
+            },
+        }
+    }
+}
+
+/// Without process groups, stop the children themselves.
+#[cfg(not(unix))]
+fn terminate(children: &mut [Child], _signal: i32, _group: bool) {
+    for child in children {
+        let _ = child.kill();
     }
 }
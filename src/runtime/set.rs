@@ -1,87 +1,110 @@
-use crate::env::Value;
+use std::sync::{Arc, Mutex};
+
+use indexmap::IndexSet;
+
+use crate::env::{HashKey, Value};
 use crate::error::{LatchError, Result};
-use std::collections::HashSet;
+
+type SetRef = Arc<Mutex<IndexSet<HashKey>>>;
 
 pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
     match method {
-        "new" => {
-            // Create new set
-            Ok(Value::new_list(vec![]))
-        }
+        // set.new() / set.new(list) — same as the set() builtin
+        "new" => match args.first() {
+            Some(v) => from_value(v),
+            None => Ok(Value::new_set([])),
+        },
 
+        // set.add(s, item) — insert in place, returns the set
         "add" => {
-            if args.len() < 2 {
-                return Err(LatchError::ArgCountMismatch { name: "set.add".into(), expected: 2, found: args.len() });
-            }
-            // Convert list to set
-            let list = args[0].clone().into_list()?;
-            let item = args[1].clone();
-            let mut set: HashSet<String> = list.iter().map(|v| format!("{}", v)).collect();
-            set.insert(format!("{}", item));
-            let new_list: Vec<Value> = set.into_iter().map(Value::Str).collect();
-            Ok(Value::new_list(new_list))
+            let set = set_arg("set.add", &args, 2)?;
+            let key = HashKey::from_value(&args[1])?;
+            set.lock().unwrap().insert(key);
+            Ok(args[0].clone())
         }
 
+        // set.remove(s, item) — remove in place, returns whether it was present
         "remove" => {
-            if args.len() < 2 {
-                return Err(LatchError::ArgCountMismatch { name: "set.remove".into(), expected: 2, found: args.len() });
-            }
-            let list = args[0].clone().into_list()?;
-            let item = args[1].clone();
-            let mut set: HashSet<String> = list.iter().map(|v| format!("{}", v)).collect();
-            set.remove(&format!("{}", item));
-            let new_list: Vec<Value> = set.into_iter().map(Value::Str).collect();
-            Ok(Value::new_list(new_list))
+            let set = set_arg("set.remove", &args, 2)?;
+            let key = HashKey::from_value(&args[1])?;
+            let removed = set.lock().unwrap().shift_remove(&key);
+            Ok(Value::Bool(removed))
         }
 
         "has" => {
-            if args.len() < 2 {
-                return Err(LatchError::ArgCountMismatch { name: "set.has".into(), expected: 2, found: args.len() });
-            }
-            let list = args[0].clone().into_list()?;
-            let item = format!("{}", args[1]);
-            let set: HashSet<String> = list.iter().map(|v| format!("{}", v)).collect();
-            Ok(Value::Bool(set.contains(&item)))
+            let set = set_arg("set.has", &args, 2)?;
+            let key = HashKey::from_value(&args[1])?;
+            let found = set.lock().unwrap().contains(&key);
+            Ok(Value::Bool(found))
         }
 
-        "union" => {
+        "union" | "intersection" | "difference" | "symmetric_difference" => {
             if args.len() < 2 {
-                return Err(LatchError::ArgCountMismatch { name: "set.union".into(), expected: 2, found: args.len() });
+                return Err(LatchError::ArgCountMismatch { name: format!("set.{method}"), expected: 2, found: args.len() });
             }
-            let list1 = args[0].clone().into_list()?;
-            let list2 = args[1].clone().into_list()?;
-            let mut set: HashSet<String> = list1.iter().map(|v| format!("{}", v)).collect();
-            set.extend(list2.iter().map(|v| format!("{}", v)));
-            let new_list: Vec<Value> = set.into_iter().map(Value::Str).collect();
-            Ok(Value::new_list(new_list))
+            combine(method, &args[0], &args[1])
         }
 
-        "intersection" => {
+        "is_subset" | "is_superset" => {
             if args.len() < 2 {
-                return Err(LatchError::ArgCountMismatch { name: "set.intersection".into(), expected: 2, found: args.len() });
+                return Err(LatchError::ArgCountMismatch { name: format!("set.{method}"), expected: 2, found: args.len() });
             }
-            let list1 = args[0].clone().into_list()?;
-            let list2 = args[1].clone().into_list()?;
-            let set1: HashSet<String> = list1.iter().map(|v| format!("{}", v)).collect();
-            let set2: HashSet<String> = list2.iter().map(|v| format!("{}", v)).collect();
-            let result: HashSet<String> = set1.intersection(&set2).cloned().collect();
-            let new_list: Vec<Value> = result.into_iter().map(Value::Str).collect();
-            Ok(Value::new_list(new_list))
+            let a = to_keys(&args[0])?;
+            let b = to_keys(&args[1])?;
+            let result = if method == "is_subset" { a.is_subset(&b) } else { a.is_superset(&b) };
+            Ok(Value::Bool(result))
         }
 
-        "difference" => {
-            if args.len() < 2 {
-                return Err(LatchError::ArgCountMismatch { name: "set.difference".into(), expected: 2, found: args.len() });
-            }
-            let list1 = args[0].clone().into_list()?;
-            let list2 = args[1].clone().into_list()?;
-            let set1: HashSet<String> = list1.iter().map(|v| format!("{}", v)).collect();
-            let set2: HashSet<String> = list2.iter().map(|v| format!("{}", v)).collect();
-            let result: HashSet<String> = set1.difference(&set2).cloned().collect();
-            let new_list: Vec<Value> = result.into_iter().map(Value::Str).collect();
-            Ok(Value::new_list(new_list))
+        "to_list" => {
+            let set = set_arg("set.to_list", &args, 1)?;
+            let items = set.lock().unwrap().iter().map(HashKey::to_value).collect();
+            Ok(Value::new_list(items))
         }
 
         _ => Err(LatchError::UnknownMethod { module: "set".into(), method: method.into() }),
     }
 }
+
+/// Build a set from a list (or copy a set). Used by `set(...)` and `set.new(...)`.
+pub fn from_value(val: &Value) -> Result<Value> {
+    Ok(Value::new_set(to_keys(val)?))
+}
+
+/// Set algebra shared by the `set.*` functions and the `| & ^ -` operators.
+/// The operands may be sets or lists; the result is always a new set.
+pub fn combine(op: &str, a: &Value, b: &Value) -> Result<Value> {
+    let a = to_keys(a)?;
+    let b = to_keys(b)?;
+    let result: IndexSet<HashKey> = match op {
+        "union" => a.union(&b).cloned().collect(),
+        "intersection" => a.intersection(&b).cloned().collect(),
+        "difference" => a.difference(&b).cloned().collect(),
+        "symmetric_difference" => a.symmetric_difference(&b).cloned().collect(),
+        _ => unreachable!("unknown set operation {op}"),
+    };
+    Ok(Value::new_set(result))
+}
+
+fn to_keys(val: &Value) -> Result<IndexSet<HashKey>> {
+    match val {
+        Value::Set(s) => Ok(s.lock().unwrap().clone()),
+        Value::List(l) => l.lock().unwrap().iter().map(HashKey::from_value).collect(),
+        other => Err(LatchError::TypeMismatch {
+            expected: "set or list".into(),
+            found: other.type_name().into(),
+        }),
+    }
+}
+
+fn set_arg(name: &str, args: &[Value], expected: usize) -> Result<SetRef> {
+    if args.len() < expected {
+        return Err(LatchError::ArgCountMismatch { name: name.into(), expected, found: args.len() });
+    }
+    match &args[0] {
+        Value::Set(s) => Ok(s.clone()),
+        other => Err(LatchError::TypeMismatch {
+            expected: "set".into(),
+            found: other.type_name().into(),
+        }),
+    }
+}
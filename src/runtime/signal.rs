@@ -0,0 +1,260 @@
+// ── Signals ──────────────────────────────────────────────────
+// SIGINT, SIGTERM and SIGHUP don't kill the interpreter outright: the handler
+// only records the signal, and the interpreter acts on it between statements
+// (running an `on_signal` trap, or unwinding with an Interrupted error so
+// `finally` blocks and defers run). Blocking calls poll `interrupted()` so a
+// long command or sleep doesn't hold the signal up. Each thread acts on a
+// signal once, so `finally` blocks and defers run undisturbed after it.
+// Running children are also noted in a fixed table the handler itself can
+// read, so a second signal that kills the interpreter takes them along.
+
+use std::cell::Cell;
+use std::sync::atomic::{AtomicI32, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::env::Value;
+use crate::error::{LatchError, Result};
+
+/// The signal waiting to be handled, or 0.
+static PENDING: AtomicI32 = AtomicI32::new(0);
+/// How many signals have arrived, so waiters can spot a new one.
+static RECEIVED: AtomicUsize = AtomicUsize::new(0);
+/// Bit per signal number with an `on_signal` trap.
+static TRAPPED: AtomicU64 = AtomicU64::new(0);
+/// Running children as `kill()` targets: a process group as its negated id,
+/// or a lone process as its pid. 0 marks a free slot.
+static CHILDREN: [AtomicI32; MAX_CHILDREN] = [const { AtomicI32::new(0) }; MAX_CHILDREN];
+
+thread_local! {
+    /// `RECEIVED` as of the last signal this thread acted on.
+    static SEEN: Cell<usize> = const { Cell::new(0) };
+}
+
+/// How often blocking calls check for a signal.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// Children tracked at once; any beyond this aren't killed by a second signal.
+const MAX_CHILDREN: usize = 64;
+
+/// The signals scripts can trap.
+#[cfg(unix)]
+const HANDLED: [i32; 3] = [libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+/// Record SIGINT, SIGTERM and SIGHUP instead of dying on them. A second one
+/// arriving before the first was handled gets the default action, so a
+/// script stuck in a blocking call can still be stopped; the children it
+/// has running are SIGKILLed first.
+#[cfg(unix)]
+pub fn install() {
+    extern "C" fn on_signal(sig: libc::c_int) {
+        if PENDING.swap(sig, Ordering::SeqCst) != 0 {
+            // SAFETY: kill(), signal() and raise() are async-signal-safe
+            unsafe {
+                for slot in &CHILDREN {
+                    let target = slot.load(Ordering::SeqCst);
+                    if target != 0 {
+                        libc::kill(target, libc::SIGKILL);
+                    }
+                }
+                libc::signal(sig, libc::SIG_DFL);
+                libc::raise(sig);
+            }
+        }
+        RECEIVED.fetch_add(1, Ordering::SeqCst);
+    }
+    for sig in HANDLED {
+        // SAFETY: the handler only touches atomics (and the calls above)
+        unsafe {
+            let mut action: libc::sigaction = std::mem::zeroed();
+            action.sa_sigaction = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+            action.sa_flags = libc::SA_RESTART;
+            libc::sigemptyset(&mut action.sa_mask);
+            libc::sigaction(sig, &action, std::ptr::null_mut());
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub fn install() {}
+
+/// A child entered in the table a second signal kills from; it leaves the
+/// table when released or dropped.
+#[derive(Debug)]
+pub struct Tracked(Option<usize>);
+
+/// Note a running child: `-pgid` for a process group, or a lone pid. If the
+/// table is full the child just isn't tracked.
+pub fn track(target: i32) -> Tracked {
+    let slot = CHILDREN.iter().position(|slot| {
+        slot.compare_exchange(0, target, Ordering::SeqCst, Ordering::SeqCst).is_ok()
+    });
+    Tracked(slot)
+}
+
+impl Tracked {
+    /// Take the child out of the table once it has exited, so its id can't
+    /// hit whatever reuses it.
+    pub fn release(&mut self) {
+        if let Some(i) = self.0.take() {
+            CHILDREN[i].store(0, Ordering::SeqCst);
+        }
+    }
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
+/// The signal waiting to be handled, if any.
+pub fn pending() -> Option<i32> {
+    match PENDING.load(Ordering::SeqCst) {
+        0 => None,
+        sig => Some(sig),
+    }
+}
+
+/// Take the pending signal, marking it handled; None if this thread has
+/// already acted on it.
+pub fn take_unseen() -> Option<i32> {
+    let sig = match PENDING.swap(0, Ordering::SeqCst) {
+        0 => return None,
+        sig => sig,
+    };
+    let received = received();
+    if SEEN.with(Cell::get) == received {
+        return None;
+    }
+    SEEN.with(|seen| seen.set(received));
+    Some(sig)
+}
+
+/// Note that this thread has acted on every signal so far.
+pub fn mark_seen() {
+    SEEN.with(|seen| seen.set(received()));
+}
+
+/// `received()` as of the last signal this thread acted on. A signal
+/// arriving after this hasn't been handled here yet.
+pub fn seen() -> usize {
+    SEEN.with(Cell::get)
+}
+
+/// Start this thread from another thread's `seen()`: a parallel item or task
+/// acts on any signal its parent hasn't, whichever thread it lands on.
+pub fn set_seen(seen: usize) {
+    SEEN.with(|s| s.set(seen));
+}
+
+/// How many signals have arrived so far.
+pub fn received() -> usize {
+    RECEIVED.load(Ordering::SeqCst)
+}
+
+/// A pending signal that no trap will handle: blocking calls give up on it.
+pub fn interrupted() -> Option<i32> {
+    pending().filter(|&sig| !is_trapped(sig))
+}
+
+/// The Interrupted error for a pending untrapped signal this thread hasn't
+/// acted on yet.
+pub fn check() -> Result<()> {
+    let received = received();
+    if SEEN.with(Cell::get) == received {
+        return Ok(());
+    }
+    match interrupted() {
+        Some(sig) => {
+            SEEN.with(|seen| seen.set(received));
+            Err(LatchError::Interrupted(sig))
+        }
+        None => Ok(()),
+    }
+}
+
+/// Sleep for `d`, cut short by a signal no trap handles.
+pub fn sleep(d: Duration) -> Result<()> {
+    let end = Instant::now() + d;
+    loop {
+        check()?;
+        let now = Instant::now();
+        if now >= end {
+            return Ok(());
+        }
+        thread::sleep(POLL_INTERVAL.min(end - now));
+    }
+}
+
+pub fn set_trapped(sig: i32, trapped: bool) {
+    let bit = 1u64 << sig;
+    if trapped {
+        TRAPPED.fetch_or(bit, Ordering::SeqCst);
+    } else {
+        TRAPPED.fetch_and(!bit, Ordering::SeqCst);
+    }
+}
+
+fn is_trapped(sig: i32) -> bool {
+    TRAPPED.load(Ordering::SeqCst) & (1u64 << sig) != 0
+}
+
+/// A signal given by name (`"TERM"`, `"SIGINT"`) or number; `default` if absent.
+#[cfg(unix)]
+pub fn number(name: &str, signal: Option<&Value>, default: i32) -> Result<i32> {
+    let text = match signal {
+        None => return Ok(default),
+        Some(Value::Int(n)) => return Ok(*n as i32),
+        Some(v) => v.as_str()?,
+    };
+    Ok(match text.trim_start_matches("SIG") {
+        "TERM" => libc::SIGTERM,
+        "KILL" => libc::SIGKILL,
+        "INT" => libc::SIGINT,
+        "HUP" => libc::SIGHUP,
+        "QUIT" => libc::SIGQUIT,
+        "USR1" => libc::SIGUSR1,
+        "USR2" => libc::SIGUSR2,
+        "STOP" => libc::SIGSTOP,
+        "CONT" => libc::SIGCONT,
+        other => return Err(LatchError::ValueError(format!("{name}: unknown signal \"{other}\""))),
+    })
+}
+
+#[cfg(not(unix))]
+pub fn number(_name: &str, _signal: Option<&Value>, default: i32) -> Result<i32> {
+    Ok(default)
+}
+
+/// A signal `on_signal` can trap.
+#[cfg(unix)]
+pub fn trappable(name: &str, sig: i32) -> Result<i32> {
+    if HANDLED.contains(&sig) {
+        Ok(sig)
+    } else {
+        Err(LatchError::ValueError(format!("{name}: only SIGINT, SIGTERM and SIGHUP can be trapped")))
+    }
+}
+
+#[cfg(not(unix))]
+pub fn trappable(_name: &str, sig: i32) -> Result<i32> {
+    Ok(sig)
+}
+
+/// `SIGINT`, `SIGTERM`, ... for messages and trap arguments.
+pub fn name(sig: i32) -> String {
+    #[cfg(unix)]
+    let known = match sig {
+        libc::SIGINT => Some("SIGINT"),
+        libc::SIGTERM => Some("SIGTERM"),
+        libc::SIGHUP => Some("SIGHUP"),
+        libc::SIGQUIT => Some("SIGQUIT"),
+        libc::SIGKILL => Some("SIGKILL"),
+        libc::SIGUSR1 => Some("SIGUSR1"),
+        libc::SIGUSR2 => Some("SIGUSR2"),
+        _ => None,
+    };
+    #[cfg(not(unix))]
+    let known: Option<&str> = None;
+    known.map(str::to_string).unwrap_or_else(|| format!("signal {sig}"))
+}
@@ -0,0 +1,67 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::error::{LatchError, Result};
+
+/// Where `pad` puts the original text.
+#[derive(Clone, Copy)]
+pub enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+/// User-perceived characters (extended grapheme clusters): "é" written as
+/// e + combining accent, or a flag emoji, is one item.
+pub fn graphemes(s: &str) -> Vec<&str> {
+    s.graphemes(true).collect()
+}
+
+/// Display width in terminal columns: wide (CJK, emoji) characters take two
+/// columns and combining marks none.
+pub fn width(s: &str) -> usize {
+    s.width()
+}
+
+/// Pad `s` with `fill` to `cols` display columns. Text already that wide is
+/// returned unchanged.
+pub fn pad(s: &str, cols: usize, fill: &str, align: Align) -> Result<String> {
+    if graphemes(fill).len() != 1 || width(fill) != 1 {
+        return Err(LatchError::ValueError(format!(
+            "pad fill must be a single one-column character, got {fill:?}"
+        )));
+    }
+    let missing = cols.saturating_sub(width(s));
+    let (left, right) = match align {
+        Align::Left => (0, missing),
+        Align::Right => (missing, 0),
+        Align::Center => (missing / 2, missing - missing / 2),
+    };
+    Ok(format!("{}{s}{}", fill.repeat(left), fill.repeat(right)))
+}
+
+/// Cut `s` to at most `cols` display columns, ending with `ellipsis` when
+/// anything was removed. Never splits a grapheme.
+pub fn truncate(s: &str, cols: usize, ellipsis: &str) -> String {
+    if width(s) <= cols {
+        return s.to_string();
+    }
+    let ellipsis = take_width(ellipsis, cols);
+    let mut out = take_width(s, cols - width(&ellipsis));
+    out.push_str(&ellipsis);
+    out
+}
+
+/// The longest prefix of `s` that fits in `cols` columns.
+fn take_width(s: &str, cols: usize) -> String {
+    let mut used = 0;
+    let mut out = String::new();
+    for g in s.graphemes(true) {
+        used += width(g);
+        if used > cols {
+            break;
+        }
+        out.push_str(g);
+    }
+    out
+}
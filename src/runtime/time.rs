@@ -1,27 +1,407 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, NaiveDateTime, Offset, TimeDelta, TimeZone, Timelike, Utc};
+use chrono_tz::Tz;
+
+use crate::ast::BinOp;
 use crate::env::Value;
 use crate::error::{LatchError, Result};
 
+type Moment = DateTime<FixedOffset>;
+
 pub fn call(method: &str, args: Vec<Value>) -> Result<Value> {
     match method {
+        // time.sleep(500) or time.sleep(5m) — an int is milliseconds
         "sleep" => {
-            let ms = args.first()
-                .ok_or_else(|| LatchError::ArgCountMismatch { name: "time.sleep".into(), expected: 1, found: 0 })?
-                .as_int()?;
-            std::thread::sleep(std::time::Duration::from_millis(ms as u64));
+            let d = duration_arg(arg(&args, 0, "time.sleep")?)?;
+            if d > TimeDelta::zero() {
+                crate::runtime::signal::sleep(d.to_std().unwrap_or_default())?;
+            }
             Ok(Value::Null)
         }
 
-        "now" => {
-            let now = chrono::Utc::now().to_rfc3339();
-            Ok(Value::Str(now))
+        // time.now(tz="UTC") — current date and time as an RFC 3339 string;
+        // time.current(tz="UTC") — the same as a datetime
+        "now" | "current" => {
+            let now = Utc::now();
+            let tz = match args.first() {
+                Some(v) => parse_tz(v.as_str()?)?.offset_at(&now),
+                None => Utc.fix(),
+            };
+            let now = now.with_timezone(&tz);
+            Ok(match method {
+                "now" => Value::Str(now.to_rfc3339()),
+                _ => Value::DateTime(now),
+            })
         }
 
         "ms" => {
             // Return milliseconds since epoch for easy subtraction
-            let ms = chrono::Utc::now().timestamp_millis();
+            let ms = Utc::now().timestamp_millis();
             Ok(Value::Int(ms))
         }
 
+        // time.parse("2024-05-01 12:00", "%Y-%m-%d %H:%M") — the format is optional
+        // for RFC 3339 / RFC 2822 and plain "YYYY-MM-DD[ HH:MM:SS]" input
+        "parse" => {
+            let text = arg(&args, 0, "time.parse")?.as_str()?;
+            match args.get(1) {
+                Some(fmt) => parse_with(text.trim(), fmt.as_str()?),
+                None => parse_auto(text.trim()),
+            }
+            .map(Value::DateTime)
+        }
+
+        // time.format(dt, "%Y-%m-%d") — strftime formatting; RFC 3339 without a format
+        "format" | "strftime" => {
+            let dt = datetime_arg(arg(&args, 0, &format!("time.{method}"))?)?;
+            match args.get(1) {
+                Some(fmt) => format(&dt, fmt.as_str()?).map(Value::Str),
+                None => Ok(Value::Str(dt.to_rfc3339())),
+            }
+        }
+
+        // time.to_tz(dt, "Europe/Berlin") — same instant, different offset
+        // ("UTC", "local", an IANA zone name or "±HH:MM")
+        "to_tz" => {
+            if args.len() < 2 {
+                return Err(LatchError::ArgCountMismatch { name: "time.to_tz".into(), expected: 2, found: args.len() });
+            }
+            let dt = datetime_arg(&args[0])?;
+            let tz = parse_tz(args[1].as_str()?)?.offset_at(&dt.with_timezone(&Utc));
+            Ok(Value::DateTime(dt.with_timezone(&tz)))
+        }
+
+        "utc" | "local" => {
+            let dt = datetime_arg(arg(&args, 0, &format!("time.{method}"))?)?;
+            let tz = parse_tz(method)?.offset_at(&dt.with_timezone(&Utc));
+            Ok(Value::DateTime(dt.with_timezone(&tz)))
+        }
+
+        // time.date(2024, 5, 1, 12, 30, 0, tz="UTC")
+        "date" => {
+            if args.len() < 3 {
+                return Err(LatchError::ArgCountMismatch { name: "time.date".into(), expected: 3, found: args.len() });
+            }
+            let mut parts = [0u32; 5];
+            for (i, part) in parts.iter_mut().enumerate() {
+                *part = match args.get(i + 1) {
+                    Some(v) => u32::try_from(v.as_int()?).map_err(|_| invalid_date())?,
+                    None => 0,
+                };
+            }
+            let [month, day, hour, minute, second] = parts;
+            let year = i32::try_from(args[0].as_int()?).map_err(|_| invalid_date())?;
+            let naive = NaiveDate::from_ymd_opt(year, month, day)
+                .and_then(|d| d.and_hms_opt(hour, minute, second))
+                .ok_or_else(invalid_date)?;
+            let zone = match args.get(6) {
+                Some(v) => parse_tz(v.as_str()?)?,
+                None => Zone::Fixed(Utc.fix()),
+            };
+            let dt = zone.wall_clock(&naive).ok_or_else(invalid_date)?;
+            Ok(Value::DateTime(dt))
+        }
+
+        // time.from_unix(1714564800) — seconds since the epoch (float for sub-second)
+        "from_unix" => {
+            let secs = arg(&args, 0, "time.from_unix")?.as_float()?;
+            let dt = DateTime::from_timestamp_millis((secs * 1000.0).round() as i64)
+                .ok_or_else(|| LatchError::ValueError(format!("timestamp {secs} out of range")))?;
+            Ok(Value::DateTime(dt.fixed_offset()))
+        }
+
+        "unix" => {
+            let dt = datetime_arg(arg(&args, 0, "time.unix")?)?;
+            Ok(Value::Int(dt.timestamp()))
+        }
+
+        // time.duration(1500) or time.duration("1h30m")
+        "duration" => Ok(Value::Duration(duration_arg(arg(&args, 0, "time.duration")?)?)),
+
+        // time.since(dt) — how long ago `dt` was
+        "since" => {
+            let dt = datetime_arg(arg(&args, 0, "time.since")?)?;
+            Ok(Value::Duration(Utc::now().fixed_offset() - dt))
+        }
+
         _ => Err(LatchError::UnknownMethod { module: "time".into(), method: method.into() }),
     }
 }
+
+/// `+ - * /` and comparisons involving datetimes and durations.
+pub fn binop(op: BinOp, l: &Value, r: &Value) -> Result<Value> {
+    let result = match (l, r) {
+        (Value::DateTime(a), Value::Duration(d)) => match op {
+            BinOp::Add => a.checked_add_signed(*d).map(Value::DateTime),
+            BinOp::Sub => a.checked_sub_signed(*d).map(Value::DateTime),
+            _ => return Err(unsupported(l, r)),
+        },
+        (Value::Duration(d), Value::DateTime(a)) if op == BinOp::Add => a.checked_add_signed(*d).map(Value::DateTime),
+        (Value::DateTime(a), Value::DateTime(b)) => match op {
+            BinOp::Sub => Some(Value::Duration(*a - *b)),
+            _ => return compare(op, a.cmp(b)).ok_or_else(|| unsupported(l, r)),
+        },
+        (Value::Duration(a), Value::Duration(b)) => match op {
+            BinOp::Add => a.checked_add(b).map(Value::Duration),
+            BinOp::Sub => a.checked_sub(b).map(Value::Duration),
+            BinOp::Div => {
+                if b.is_zero() { return Err(LatchError::DivisionByZero); }
+                Some(Value::Float(a.num_milliseconds() as f64 / b.num_milliseconds() as f64))
+            }
+            _ => return compare(op, a.cmp(b)).ok_or_else(|| unsupported(l, r)),
+        },
+        (Value::Duration(d), Value::Int(_) | Value::Float(_)) | (Value::Int(_) | Value::Float(_), Value::Duration(d)) => {
+            let n = if matches!(l, Value::Duration(_)) { r.as_float()? } else { l.as_float()? };
+            let ms = d.num_milliseconds() as f64;
+            let scaled = match op {
+                BinOp::Mul => ms * n,
+                BinOp::Div if matches!(l, Value::Duration(_)) => {
+                    if n == 0.0 { return Err(LatchError::DivisionByZero); }
+                    ms / n
+                }
+                _ => return Err(unsupported(l, r)),
+            };
+            Some(Value::Duration(round_millis(scaled)?))
+        }
+        _ => return Err(unsupported(l, r)),
+    };
+    result.ok_or_else(out_of_range)
+}
+
+/// Fields readable with `dt.year`, `dt.weekday`, ...
+pub fn datetime_field(dt: &Moment, field: &str) -> Option<Value> {
+    let n = |v: u32| Some(Value::Int(v as i64));
+    match field {
+        "year"    => Some(Value::Int(dt.year() as i64)),
+        "month"   => n(dt.month()),
+        "day"     => n(dt.day()),
+        "hour"    => n(dt.hour()),
+        "minute"  => n(dt.minute()),
+        "second"  => n(dt.second()),
+        "ms"      => n(dt.timestamp_subsec_millis()),
+        "weekday" => n(dt.weekday().number_from_monday()),
+        "yday"    => n(dt.ordinal()),
+        "unix"    => Some(Value::Int(dt.timestamp())),
+        "offset"  => Some(Value::Str(dt.offset().to_string())),
+        _ => None,
+    }
+}
+
+/// Fields readable with `d.seconds`, `d.ms`, ... — each is the whole duration in that unit.
+pub fn duration_field(d: TimeDelta, field: &str) -> Option<Value> {
+    let n = match field {
+        "ms"      => d.num_milliseconds(),
+        "seconds" => d.num_seconds(),
+        "minutes" => d.num_minutes(),
+        "hours"   => d.num_hours(),
+        "days"    => d.num_days(),
+        _ => return None,
+    };
+    Some(Value::Int(n))
+}
+
+/// A duration of `ms` milliseconds, if it's within the range a duration can hold.
+pub fn millis(ms: i64) -> Result<TimeDelta> {
+    TimeDelta::try_milliseconds(ms).ok_or_else(out_of_range)
+}
+
+/// `ms` rounded to whole milliseconds, if a duration can hold it.
+fn round_millis(ms: f64) -> Result<TimeDelta> {
+    let ms = ms.round();
+    if ms.is_nan() || ms.abs() >= i64::MAX as f64 {
+        return Err(out_of_range());
+    }
+    millis(ms as i64)
+}
+
+/// Render a duration the way it would be written as a literal: `1h30m`, `250ms`, `-5s`.
+pub fn format_duration(d: TimeDelta) -> String {
+    let total = d.num_milliseconds();
+    if total == 0 {
+        return "0s".into();
+    }
+    let mut rest = total.unsigned_abs();
+    let mut out = String::from(if total < 0 { "-" } else { "" });
+    for (unit, size) in [("d", 86_400_000), ("h", 3_600_000), ("m", 60_000), ("s", 1_000), ("ms", 1)] {
+        if rest >= size {
+            out.push_str(&format!("{}{unit}", rest / size));
+            rest %= size;
+        }
+    }
+    out
+}
+
+/// Parse `"1h30m"`, `"250ms"`, `"1.5d"` or `"-10s"`.
+pub fn parse_duration(text: &str) -> Result<TimeDelta> {
+    let invalid = || LatchError::ValueError(format!("invalid duration '{text}' (expected e.g. \"250ms\", \"5m\" or \"1h30m\")"));
+    let (negative, mut rest) = match text.trim().strip_prefix('-') {
+        Some(r) => (true, r),
+        None => (false, text.trim()),
+    };
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+    let mut total = 0.0;
+    while !rest.is_empty() {
+        let num_len = rest.find(|c: char| !(c.is_ascii_digit() || c == '.')).ok_or_else(invalid)?;
+        let unit_len = rest[num_len..].find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len() - num_len);
+        let value: f64 = rest[..num_len].parse().map_err(|_| invalid())?;
+        let factor = match &rest[num_len..num_len + unit_len] {
+            "ms" => 1.0,
+            "s" => 1_000.0,
+            "m" => 60_000.0,
+            "h" => 3_600_000.0,
+            "d" => 86_400_000.0,
+            _ => return Err(invalid()),
+        };
+        total += value * factor;
+        rest = &rest[num_len + unit_len..];
+    }
+    round_millis(if negative { -total } else { total })
+}
+
+fn parse_auto(text: &str) -> Result<Moment> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(text) {
+        return Ok(dt);
+    }
+    if let Ok(dt) = DateTime::parse_from_rfc2822(text) {
+        return Ok(dt);
+    }
+    for fmt in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%d"] {
+        if let Ok(dt) = parse_with(text, fmt) {
+            return Ok(dt);
+        }
+    }
+    Err(LatchError::ValueError(format!("time.parse: unrecognised date '{text}' (pass a format string)")))
+}
+
+/// Parse with a strftime format. Formats without an offset are read as UTC,
+/// and date-only formats as midnight.
+fn parse_with(text: &str, fmt: &str) -> Result<Moment> {
+    if let Ok(dt) = DateTime::parse_from_str(text, fmt) {
+        return Ok(dt);
+    }
+    if let Ok(naive) = NaiveDateTime::parse_from_str(text, fmt) {
+        return Ok(naive.and_utc().fixed_offset());
+    }
+    match NaiveDate::parse_from_str(text, fmt) {
+        Ok(date) => Ok(date.and_time(Default::default()).and_utc().fixed_offset()),
+        Err(e) => Err(LatchError::ValueError(format!("time.parse: cannot read '{text}' as '{fmt}': {e}"))),
+    }
+}
+
+fn format(dt: &Moment, fmt: &str) -> Result<String> {
+    let items: Vec<Item> = StrftimeItems::new(fmt).collect();
+    if items.contains(&Item::Error) {
+        return Err(LatchError::ValueError(format!("invalid time format '{fmt}'")));
+    }
+    Ok(dt.format_with_items(items.into_iter()).to_string())
+}
+
+/// A timezone argument. Datetimes only keep an offset, so a zone is resolved
+/// to the offset in effect at the instant in question.
+enum Zone {
+    Fixed(FixedOffset),
+    Local,
+    /// An IANA zone such as `Europe/Berlin`, following its DST rules
+    Named(Tz),
+}
+
+impl Zone {
+    /// The zone's offset at `at`.
+    fn offset_at(&self, at: &DateTime<Utc>) -> FixedOffset {
+        match self {
+            Zone::Fixed(offset) => *offset,
+            Zone::Local => at.with_timezone(&Local).offset().fix(),
+            Zone::Named(tz) => at.with_timezone(tz).offset().fix(),
+        }
+    }
+
+    /// `naive` read as wall-clock time in the zone; None for a time skipped
+    /// or repeated by a DST change.
+    fn wall_clock(&self, naive: &NaiveDateTime) -> Option<Moment> {
+        match self {
+            Zone::Fixed(offset) => offset.from_local_datetime(naive).single(),
+            Zone::Local => Local.from_local_datetime(naive).single().map(|dt| dt.fixed_offset()),
+            Zone::Named(tz) => tz.from_local_datetime(naive).single().map(|dt| dt.fixed_offset()),
+        }
+    }
+}
+
+/// "UTC", "local", an IANA name like "Europe/Berlin", or a fixed offset like
+/// "+05:30" / "-0800".
+fn parse_tz(tz: &str) -> Result<Zone> {
+    match tz.to_ascii_lowercase().as_str() {
+        "utc" | "z" => return Ok(Zone::Fixed(Utc.fix())),
+        "local" => return Ok(Zone::Local),
+        _ => {}
+    }
+    if let Ok(named) = tz.parse::<Tz>() {
+        return Ok(Zone::Named(named));
+    }
+    let invalid = || LatchError::ValueError(format!(
+        "unknown timezone '{tz}' (expected \"UTC\", \"local\", a zone name like \"Europe/Berlin\" or an offset like \"+02:00\")"
+    ));
+    let (sign, digits) = match tz.split_at_checked(1) {
+        Some(("+", d)) => (1, d),
+        Some(("-", d)) => (-1, d),
+        _ => return Err(invalid()),
+    };
+    let digits = digits.replace(':', "");
+    if !digits.chars().all(|c| c.is_ascii_digit()) || !matches!(digits.len(), 2 | 4) {
+        return Err(invalid());
+    }
+    let hours: i32 = digits[..2].parse().map_err(|_| invalid())?;
+    let minutes: i32 = if digits.len() == 4 { digits[2..].parse().map_err(|_| invalid())? } else { 0 };
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).map(Zone::Fixed).ok_or_else(invalid)
+}
+
+fn compare(op: BinOp, ord: std::cmp::Ordering) -> Option<Value> {
+    use std::cmp::Ordering::*;
+    let result = match op {
+        BinOp::Eq    => ord == Equal,
+        BinOp::NotEq => ord != Equal,
+        BinOp::Lt    => ord == Less,
+        BinOp::Gt    => ord == Greater,
+        BinOp::LtEq  => ord != Greater,
+        BinOp::GtEq  => ord != Less,
+        _ => return None,
+    };
+    Some(Value::Bool(result))
+}
+
+fn unsupported(l: &Value, r: &Value) -> LatchError {
+    LatchError::TypeMismatch {
+        expected: "compatible types".into(),
+        found: format!("{} and {}", l.type_name(), r.type_name()),
+    }
+}
+
+fn out_of_range() -> LatchError {
+    LatchError::ValueError("date/time arithmetic out of range".into())
+}
+
+fn invalid_date() -> LatchError {
+    LatchError::ValueError("time.date: invalid date or time".into())
+}
+
+fn datetime_arg(val: &Value) -> Result<Moment> {
+    match val {
+        Value::DateTime(dt) => Ok(*dt),
+        Value::Str(s) => parse_auto(s.trim()),
+        other => Err(LatchError::TypeMismatch { expected: "datetime".into(), found: other.type_name().into() }),
+    }
+}
+
+fn duration_arg(val: &Value) -> Result<TimeDelta> {
+    match val {
+        Value::Duration(d) => Ok(*d),
+        Value::Int(ms) => millis(*ms),
+        Value::Str(s) => parse_duration(s),
+        other => Err(LatchError::TypeMismatch { expected: "duration or int (ms)".into(), found: other.type_name().into() }),
+    }
+}
+
+fn arg<'a>(args: &'a [Value], i: usize, name: &str) -> Result<&'a Value> {
+    args.get(i).ok_or_else(|| LatchError::ArgCountMismatch { name: name.into(), expected: i + 1, found: args.len() })
+}
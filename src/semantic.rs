@@ -7,9 +7,29 @@ use crate::error::LatchError;
 pub struct SemanticAnalyzer {
     scopes: Vec<HashMap<String, SymbolInfo>>,
     current_fn: Option<String>,
+    /// Loops and `parallel` bodies enclosing the current statement, innermost
+    /// last; reset at function boundaries.
+    loops: Vec<LoopFrame>,
+    /// Declared enums: variant names and payload arity, in declaration order.
+    enums: HashMap<String, Vec<(String, usize)>>,
+    /// Declared classes, for checking `implements` against inherited methods.
+    classes: HashMap<String, ClassInfo>,
+    /// Declared interfaces: method names and parameter counts.
+    interfaces: HashMap<String, Vec<(String, usize)>>,
     errors: Vec<LatchError>,
 }
 
+struct ClassInfo {
+    parent: Option<String>,
+    methods: HashMap<String, SymbolInfo>,
+}
+
+/// What `break`/`continue` can reach from the current statement.
+enum LoopFrame {
+    Loop(Option<String>),
+    Parallel,
+}
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct SymbolInfo {
@@ -20,7 +40,7 @@ pub struct SymbolInfo {
 #[derive(Debug, Clone)]
 pub enum SymbolKind {
     Variable,
-    Function { param_count: usize },
+    Function { min_args: usize, max_args: usize },
 }
 
 impl SymbolInfo {
@@ -33,7 +53,21 @@ impl SymbolInfo {
     }
 
     fn function(param_count: usize) -> Self {
-        SymbolInfo { kind: SymbolKind::Function { param_count }, type_ann: None }
+        Self::function_range(param_count, param_count)
+    }
+
+    /// A user function: parameters with defaults are optional, and a
+    /// `...rest` parameter lifts the upper bound.
+    fn for_params(params: &[Param]) -> Self {
+        let positional = params.iter().take_while(|p| p.kind == ParamKind::Normal);
+        let min = positional.clone().filter(|p| p.default.is_none()).count();
+        let max = if params.iter().any(|p| p.kind == ParamKind::Rest) { usize::MAX } else { positional.count() };
+        Self::function_range(min, max)
+    }
+
+    /// A function accepting between `min_args` and `max_args` arguments.
+    fn function_range(min_args: usize, max_args: usize) -> Self {
+        SymbolInfo { kind: SymbolKind::Function { min_args, max_args }, type_ann: None }
     }
 }
 
@@ -42,6 +76,10 @@ impl SemanticAnalyzer {
         SemanticAnalyzer {
             scopes: Vec::new(),
             current_fn: None,
+            loops: Vec::new(),
+            enums: HashMap::new(),
+            classes: HashMap::new(),
+            interfaces: HashMap::new(),
             errors: Vec::new(),
         }
     }
@@ -148,12 +186,44 @@ impl SemanticAnalyzer {
         self.declare("str_isdigit", SymbolInfo::function(1));
         self.declare("str_isalpha", SymbolInfo::function(1));
         self.declare("str_capitalize", SymbolInfo::function(1));
+        self.declare("str_width", SymbolInfo::function(1));
+        self.declare("chars", SymbolInfo::function(1));
+        self.declare("graphemes", SymbolInfo::function(1));
+        self.declare("pad_left", SymbolInfo::function_range(2, 3));
+        self.declare("pad_right", SymbolInfo::function_range(2, 3));
+        self.declare("center", SymbolInfo::function_range(2, 3));
+        self.declare("truncate", SymbolInfo::function_range(2, 3));
         
         // Higher-order functions
-        self.declare("sort", SymbolInfo::function(1));
+        self.declare("sort", SymbolInfo::function_range(1, 2));
+        self.declare("sorted_by", SymbolInfo::function_range(2, 3));
+        self.declare("min_by", SymbolInfo::function(2));
+        self.declare("max_by", SymbolInfo::function(2));
+        self.declare("group_by", SymbolInfo::function(2));
+        self.declare("unique_by", SymbolInfo::function(2));
         self.declare("filter", SymbolInfo::function(2));
         self.declare("map", SymbolInfo::function(2));
         self.declare("each", SymbolInfo::function(2));
+        self.declare("set", SymbolInfo::function_range(0, 1));
+        self.declare("bytes", SymbolInfo::function_range(1, 2));
+
+        // Results
+        self.declare("ok", SymbolInfo::function(1));
+        self.declare("err", SymbolInfo::function(1));
+        self.declare("unwrap", SymbolInfo::function(1));
+
+        // Classes
+        self.declare("is_a", SymbolInfo::function(2));
+
+        // Signals
+        self.declare("on_signal", SymbolInfo::function(2));
+
+        // Background tasks
+        self.declare("await", SymbolInfo::function_range(1, 2));
+        self.declare("is_done", SymbolInfo::function(1));
+        self.declare("cancel", SymbolInfo::function(1));
+        self.declare("await_all", SymbolInfo::function_range(1, 2));
+        self.declare("await_any", SymbolInfo::function_range(1, 2));
 
         // Modules are not functions — they're resolved via ModuleCall,
         // but we register them as variables so `fs` doesn't trigger "undefined".
@@ -202,10 +272,11 @@ impl SemanticAnalyzer {
                         self.errors.push(LatchError::DuplicateFn(name.clone()));
                     }
                 }
-                self.declare(name, SymbolInfo::function(params.len()));
+                self.declare(name, SymbolInfo::for_params(params));
 
                 self.push_scope();
                 let prev = self.current_fn.take();
+                let outer_loops = std::mem::take(&mut self.loops);
                 self.current_fn = Some(name.clone());
                 for p in params {
                     self.declare(&p.name, SymbolInfo::variable());
@@ -214,6 +285,7 @@ impl SemanticAnalyzer {
                     self.check_stmt(s);
                 }
                 self.current_fn = prev;
+                self.loops = outer_loops;
                 self.pop_scope();
             }
 
@@ -244,19 +316,41 @@ impl SemanticAnalyzer {
                 }
             }
 
-            Stmt::For { var, iter, body } => {
+            Stmt::For { label, var, iter, body, else_body } => {
                 self.check_expr(iter);
                 self.push_scope();
                 self.declare(var, SymbolInfo::variable());
+                self.loops.push(LoopFrame::Loop(label.clone()));
                 for s in body { self.check_stmt(s); }
+                self.loops.pop();
                 self.pop_scope();
+                self.check_loop_else(else_body);
             }
 
-            Stmt::Parallel { var, iter, workers, body } => {
+            Stmt::Parallel { var, iter, workers, output, body } => {
                 self.check_expr(iter);
                 if let Some(w) = workers { self.check_expr(w); }
+                if let Some(o) = output { self.check_expr(o); }
                 self.push_scope();
                 self.declare(var, SymbolInfo::variable());
+                self.loops.push(LoopFrame::Parallel);
+                for s in body { self.check_stmt(s); }
+                self.loops.pop();
+                self.pop_scope();
+            }
+
+            Stmt::Defer(body) => {
+                self.push_scope();
+                for s in body { self.check_stmt(s); }
+                self.pop_scope();
+            }
+
+            Stmt::With { resource, name, body } => {
+                self.check_expr(resource);
+                self.push_scope();
+                if let Some(n) = name {
+                    self.declare(n, SymbolInfo::variable());
+                }
                 for s in body { self.check_stmt(s); }
                 self.pop_scope();
             }
@@ -289,6 +383,10 @@ impl SemanticAnalyzer {
                 self.check_expr(expr);
             }
 
+            Stmt::SetOption { value, .. } => {
+                self.check_expr(value);
+            }
+
             Stmt::Const { name, type_ann, value } => {
                 self.check_expr(value);
                 if let Some(ann) = type_ann {
@@ -301,16 +399,24 @@ impl SemanticAnalyzer {
                 self.check_expr(expr);
             }
 
-            Stmt::While { cond, body } => {
+            Stmt::While { label, cond, body, else_body } => {
                 self.check_expr(cond);
                 self.push_scope();
+                self.loops.push(LoopFrame::Loop(label.clone()));
                 for s in body { self.check_stmt(s); }
+                self.loops.pop();
                 self.pop_scope();
+                self.check_loop_else(else_body);
             }
 
-            Stmt::Break => {}
+            Stmt::Break(label) => self.check_loop_control("break", label),
 
-            Stmt::Continue => {}
+            Stmt::Continue(label) => self.check_loop_control("continue", label),
+
+            Stmt::FieldAssign { target, value, .. } => {
+                self.check_expr(target);
+                self.check_expr(value);
+            }
 
             Stmt::CompoundAssign { name, value, .. } => {
                 if self.resolve(name).is_none() {
@@ -323,26 +429,62 @@ impl SemanticAnalyzer {
                 self.check_expr(expr);
             }
 
-            Stmt::Class { name, fields, methods } => {
+            Stmt::Enum { name, variants } => {
                 self.declare(name, SymbolInfo::variable());
-                for (_field_name, type_ann, default) in fields {
-                    if let Some(_ann) = type_ann {
-                        // Type checking would go here
+                let arities = variants.iter().map(|v| (v.name.clone(), v.fields.len())).collect();
+                self.enums.insert(name.clone(), arities);
+            }
+
+            Stmt::Class { name, parent, interfaces, fields, methods } => {
+                if let Some(p) = parent {
+                    if self.resolve(p).is_none() {
+                        self.errors.push(LatchError::UndefinedVariable(p.clone()));
                     }
+                }
+                let info = ClassInfo {
+                    parent: parent.clone(),
+                    methods: methods.iter()
+                        .map(|(m, params, _)| (m.clone(), SymbolInfo::for_params(params)))
+                        .collect(),
+                };
+                self.classes.insert(name.clone(), info);
+                // Calling the class runs `init`; without one only keyword
+                // arguments (setting fields) are accepted
+                let ctor = self.class_method(name, "init").cloned().unwrap_or_else(|| SymbolInfo::function(0));
+                self.declare(name, ctor);
+
+                for iface in interfaces {
+                    self.check_implements(name, iface);
+                }
+                for (_field_name, _type_ann, default) in fields {
                     if let Some(val) = default {
                         self.check_expr(val);
                     }
                 }
-                for (_method_name, params, body) in methods {
+                for (method_name, params, body) in methods {
                     self.push_scope();
+                    let prev = self.current_fn.replace(format!("{name}.{method_name}"));
+                    let outer_loops = std::mem::take(&mut self.loops);
+                    self.declare("self", SymbolInfo::variable());
+                    if parent.is_some() {
+                        self.declare("super", SymbolInfo::variable());
+                    }
                     for param in params {
                         self.declare(&param.name, SymbolInfo::variable());
                     }
                     for s in body { self.check_stmt(s); }
+                    self.current_fn = prev;
+                    self.loops = outer_loops;
                     self.pop_scope();
                 }
             }
 
+            Stmt::Interface { name, methods } => {
+                self.declare(name, SymbolInfo::variable());
+                let sigs = methods.iter().map(|(m, params)| (m.clone(), params.len())).collect();
+                self.interfaces.insert(name.clone(), sigs);
+            }
+
             Stmt::Export(names) => {
                 for name in names {
                     if self.resolve(name).is_none() {
@@ -370,28 +512,33 @@ impl SemanticAnalyzer {
                 }
             }
 
-            Expr::Call { name, args, kwargs: _ } => {
-                match self.resolve(name) {
-                    None => {
-                        self.errors.push(LatchError::UndefinedFunction(name.clone()));
-                    }
-                    Some(SymbolInfo { kind: SymbolKind::Function { param_count }, .. }) => {
-                        let pc = *param_count;
-                        if args.len() != pc {
-                            self.errors.push(LatchError::ArgCountMismatch {
-                                name: name.clone(),
-                                expected: pc,
-                                found: args.len(),
-                            });
-                        }
-                    }
-                    _ => {}
+            Expr::Call { name, args, kwargs } => {
+                if self.resolve(name).is_none() {
+                    self.errors.push(LatchError::UndefinedFunction(name.clone()));
                 }
+                self.check_call_arity(name, args, kwargs, 0);
                 for arg in args { self.check_expr(arg); }
+                for (_, v) in kwargs { self.check_expr(v); }
             }
 
-            Expr::ModuleCall { args, .. } => {
+            Expr::ModuleCall { module, method, args, kwargs } => {
+                let spread = args.iter().any(|a| matches!(a, Expr::Spread(_)));
+                self.check_variant(module, method, if spread { None } else { Some(args.len()) });
+                if module == "super" && self.resolve("super").is_none() {
+                    self.errors.push(LatchError::UndefinedVariable(module.clone()));
+                }
                 for arg in args { self.check_expr(arg); }
+                for (_, v) in kwargs { self.check_expr(v); }
+            }
+
+            Expr::MethodCall { object, args, kwargs, .. } => {
+                self.check_expr(object);
+                for arg in args { self.check_expr(arg); }
+                for (_, v) in kwargs { self.check_expr(v); }
+            }
+
+            Expr::Spread(inner) => {
+                self.check_expr(inner);
             }
 
             Expr::BinOp { left, right, .. } => {
@@ -408,12 +555,45 @@ impl SemanticAnalyzer {
                 self.check_expr(default);
             }
 
+            Expr::TryResult(expr) => self.check_expr(expr),
+
+            Expr::Match { subject, arms } => {
+                self.check_expr(subject);
+                for arm in arms {
+                    self.push_scope();
+                    for pattern in &arm.patterns {
+                        self.check_pattern(pattern);
+                    }
+                    if let Some(guard) = &arm.guard {
+                        self.check_expr(guard);
+                    }
+                    match &arm.body {
+                        MatchBody::Expr(e) => self.check_expr(e),
+                        MatchBody::Block(body) => {
+                            for s in body { self.check_stmt(s); }
+                        }
+                    }
+                    self.pop_scope();
+                }
+                self.check_exhaustive(arms);
+            }
+
+            Expr::Propagate(expr) => {
+                if self.current_fn.is_none() {
+                    self.errors.push(LatchError::PropagateOutsideFn);
+                }
+                self.check_expr(expr);
+            }
+
             Expr::Index { expr, index } => {
                 self.check_expr(expr);
                 self.check_expr(index);
             }
 
-            Expr::FieldAccess { expr, .. } => {
+            Expr::FieldAccess { expr, field } => {
+                if let Expr::Ident(name) = &**expr {
+                    self.check_variant(name, field, None);
+                }
                 self.check_expr(expr);
             }
 
@@ -442,13 +622,26 @@ impl SemanticAnalyzer {
                 for item in items { self.check_expr(item); }
             }
 
+            Expr::Set(items) => {
+                for item in items { self.check_expr(item); }
+            }
+
             Expr::Map(entries) => {
-                for (_, v) in entries { self.check_expr(v); }
+                for entry in entries {
+                    match entry {
+                        MapEntry::Pair(k, v) => {
+                            self.check_expr(k);
+                            self.check_expr(v);
+                        }
+                        MapEntry::Spread(v) => self.check_expr(v),
+                    }
+                }
             }
 
             Expr::Fn { params, body } => {
                 self.push_scope();
                 let prev = self.current_fn.take();
+                let outer_loops = std::mem::take(&mut self.loops);
                 self.current_fn = Some("<anonymous>".to_string());
                 for p in params {
                     self.declare(&p.name, SymbolInfo::variable());
@@ -457,10 +650,11 @@ impl SemanticAnalyzer {
                     self.check_stmt(s);
                 }
                 self.current_fn = prev;
+                self.loops = outer_loops;
                 self.pop_scope();
             }
 
-            Expr::Interpolated(parts) => {
+            Expr::Interpolated(parts) | Expr::ShellCommand(parts) => {
                 // We don't deep-check interpolation sub-expressions in semantic
                 // because they're re-parsed at runtime. Could be improved.
                 let _ = parts;
@@ -473,27 +667,36 @@ impl SemanticAnalyzer {
                 self.check_expr(false_branch);
             }
 
-            // List comprehension: [body for var in iter if cond]
-            Expr::ListComp { body, var, iter, cond } => {
-                self.check_expr(iter);
+            // Comprehensions: [body for var in iter if cond], {body for ...}, {k: v for ...}
+            Expr::ListComp { body, clauses } | Expr::SetComp { body, clauses } => {
                 self.push_scope();
-                self.declare(var, SymbolInfo::variable());
+                self.check_comp_clauses(clauses);
                 self.check_expr(body);
-                if let Some(c) = cond {
-                    self.check_expr(c);
-                }
+                self.pop_scope();
+            }
+            Expr::DictComp { key, value, clauses } => {
+                self.push_scope();
+                self.check_comp_clauses(clauses);
+                self.check_expr(key);
+                self.check_expr(value);
                 self.pop_scope();
             }
 
-            // Slice: list[1:5], list[2:], list[:-1]
-            Expr::Slice { expr, start, end } => {
+            // Slice: list[1:5], list[2:], list[:-1], s[::-1]
+            Expr::Slice { expr, start, end, step } => {
                 self.check_expr(expr);
                 if let Some(s) = start { self.check_expr(s); }
                 if let Some(e) = end { self.check_expr(e); }
+                if let Some(st) = step { self.check_expr(st); }
+            }
+
+            // Background task: spawn fn() { ... }
+            Expr::Spawn(func) => {
+                self.check_expr(func);
             }
 
             // Literals — no checks needed
-            Expr::Int(_) | Expr::Float(_) | Expr::Bool(_) | Expr::Str(_) | Expr::Null => {}
+            Expr::Int(_) | Expr::Float(_) | Expr::Duration(_) | Expr::Bool(_) | Expr::Str(_) | Expr::Null => {}
         }
     }
 
@@ -501,10 +704,12 @@ impl SemanticAnalyzer {
         let found = match value {
             Expr::Int(_) => Some(Type::Int),
             Expr::Float(_) => Some(Type::Float),
+            Expr::Duration(_) => Some(Type::Duration),
             Expr::Bool(_) => Some(Type::Bool),
-            Expr::Str(_) | Expr::Interpolated(_) => Some(Type::Str),
+            Expr::Str(_) | Expr::Interpolated(_) | Expr::ShellCommand(_) => Some(Type::Str),
             Expr::List(_) => Some(Type::List),
             Expr::Map(_) => Some(Type::Dict),
+            Expr::Set(_) => Some(Type::Set),
             _ => None, // can't determine at compile time
         };
         if let Some(found) = found {
@@ -518,25 +723,199 @@ impl SemanticAnalyzer {
         }
     }
 
+    /// Positional arity check for `name(args)` plus `implicit` extra leading
+    /// args. A spread makes the count unknown, and keyword arguments may
+    /// fill required parameters, so those only bound it from above.
+    fn check_call_arity(&mut self, name: &str, args: &[Expr], kwargs: &[(String, Expr)], implicit: usize) {
+        let Some(SymbolInfo { kind: SymbolKind::Function { min_args, max_args }, .. }) = self.resolve(name) else {
+            return;
+        };
+        if args.iter().any(|a| matches!(a, Expr::Spread(_))) {
+            return;
+        }
+        let found = args.len() + implicit;
+        let min = if kwargs.is_empty() { *min_args } else { 0 };
+        if let Some(expected) = arity_mismatch(min, *max_args, found) {
+            self.errors.push(LatchError::ArgCountMismatch { name: name.to_string(), expected, found });
+        }
+    }
+
+    /// A loop's `else` block runs after the loop, so `break` there targets an outer loop.
+    fn check_loop_else(&mut self, else_body: &Option<Block>) {
+        if let Some(block) = else_body {
+            self.push_scope();
+            for s in block { self.check_stmt(s); }
+            self.pop_scope();
+        }
+    }
+
+    /// `break`/`continue` must reach a loop (the one named by `label`, if any)
+    /// without leaving a function or a `parallel` body.
+    fn check_loop_control(&mut self, keyword: &str, label: &Option<String>) {
+        for frame in self.loops.iter().rev() {
+            match frame {
+                LoopFrame::Parallel => {
+                    self.errors.push(LatchError::LoopControlInParallel(keyword.to_string()));
+                    return;
+                }
+                LoopFrame::Loop(name) if label.is_none() || name == label => return,
+                LoopFrame::Loop(_) => {}
+            }
+        }
+        self.errors.push(match label {
+            Some(l) => LatchError::UndefinedLabel(l.clone()),
+            None => LatchError::LoopControlOutsideLoop(keyword.to_string()),
+        });
+    }
+
+    /// A method of a declared class, found along its parent chain.
+    fn class_method(&self, class: &str, method: &str) -> Option<&SymbolInfo> {
+        let mut current = self.classes.get(class);
+        while let Some(info) = current {
+            if let Some(sig) = info.methods.get(method) {
+                return Some(sig);
+            }
+            current = info.parent.as_ref().and_then(|p| self.classes.get(p));
+        }
+        None
+    }
+
+    /// `class C implements I`: every method `I` declares must exist on `C` (or
+    /// a parent) and accept that many arguments. Interfaces from other files
+    /// are checked at runtime instead.
+    fn check_implements(&mut self, class: &str, iface: &str) {
+        let Some(sigs) = self.interfaces.get(iface) else {
+            if self.resolve(iface).is_none() {
+                self.errors.push(LatchError::UndefinedVariable(iface.to_string()));
+            }
+            return;
+        };
+        let mut errors = Vec::new();
+        for (method, expected) in sigs {
+            match self.class_method(class, method).map(|sig| &sig.kind) {
+                None => errors.push(LatchError::MissingInterfaceMethod {
+                    class: class.to_string(),
+                    interface: iface.to_string(),
+                    method: method.clone(),
+                }),
+                Some(SymbolKind::Function { min_args, max_args }) if arity_mismatch(*min_args, *max_args, *expected).is_some() => {
+                    errors.push(LatchError::InterfaceMethodMismatch {
+                        class: class.to_string(),
+                        interface: iface.to_string(),
+                        method: method.clone(),
+                        expected: *expected,
+                    });
+                }
+                Some(_) => {}
+            }
+        }
+        self.errors.extend(errors);
+    }
+
+    /// `Enum.Variant` / `Enum.Variant(args)` on an enum declared in this
+    /// file: the variant must exist and, when called, take that many values.
+    fn check_variant(&mut self, enum_name: &str, variant: &str, args: Option<usize>) {
+        let Some(variants) = self.enums.get(enum_name) else { return };
+        let Some((_, arity)) = variants.iter().find(|(v, _)| v == variant) else {
+            self.errors.push(LatchError::UnknownVariant {
+                enum_name: enum_name.to_string(),
+                variant: variant.to_string(),
+            });
+            return;
+        };
+        if let Some(found) = args {
+            if found != *arity {
+                self.errors.push(LatchError::ArgCountMismatch {
+                    name: format!("{enum_name}.{variant}"),
+                    expected: *arity,
+                    found,
+                });
+            }
+        }
+    }
+
+    /// Declare the names a pattern binds (the caller owns the arm's scope).
+    fn check_pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Wildcard => {}
+            Pattern::Binding(name) => self.declare(name, SymbolInfo::variable()),
+            Pattern::Literal(expr) => self.check_expr(expr),
+            Pattern::Variant { enum_name, variant, bindings } => {
+                if self.resolve(enum_name).is_none() {
+                    self.errors.push(LatchError::UndefinedVariable(enum_name.clone()));
+                }
+                self.check_variant(enum_name, variant, bindings.as_ref().map(Vec::len));
+                for name in bindings.iter().flatten().filter(|n| *n != "_") {
+                    self.declare(name, SymbolInfo::variable());
+                }
+            }
+        }
+    }
+
+    /// A `match` whose patterns name variants of one declared enum must cover
+    /// every variant, unless an unguarded `_` or binding arm catches the rest.
+    /// Guarded arms never count towards coverage.
+    fn check_exhaustive(&mut self, arms: &[MatchArm]) {
+        let unguarded = || arms.iter().filter(|a| a.guard.is_none()).flat_map(|a| &a.patterns);
+        if unguarded().any(|p| matches!(p, Pattern::Wildcard | Pattern::Binding(_))) {
+            return;
+        }
+        let mut enum_names = arms.iter().flat_map(|a| &a.patterns).filter_map(|p| match p {
+            Pattern::Variant { enum_name, .. } => Some(enum_name),
+            _ => None,
+        });
+        let Some(enum_name) = enum_names.next() else { return };
+        if enum_names.any(|n| n != enum_name) {
+            return;
+        }
+        let Some(variants) = self.enums.get(enum_name) else { return };
+        let covered: Vec<&String> = unguarded().filter_map(|p| match p {
+            Pattern::Variant { variant, .. } => Some(variant),
+            _ => None,
+        }).collect();
+        let missing: Vec<String> = variants.iter()
+            .map(|(v, _)| v)
+            .filter(|v| !covered.contains(v))
+            .map(|v| format!("{enum_name}.{v}"))
+            .collect();
+        if !missing.is_empty() {
+            self.errors.push(LatchError::NonExhaustiveMatch { enum_name: enum_name.clone(), missing });
+        }
+    }
+
+    /// Check comprehension clauses in order, declaring each loop variable for
+    /// the clauses after it (the caller owns the scope).
+    fn check_comp_clauses(&mut self, clauses: &[CompClause]) {
+        for clause in clauses {
+            match clause {
+                CompClause::For { vars, iter } => {
+                    self.check_expr(iter);
+                    for var in vars {
+                        self.declare(var, SymbolInfo::variable());
+                    }
+                }
+                CompClause::If(cond) => self.check_expr(cond),
+            }
+        }
+    }
+
     /// Check a pipe‐target expression, accounting for the implicit first argument.
     fn check_pipe_func(&mut self, func: &Expr) {
         match func {
-            Expr::Call { name, args, kwargs: _ } => {
+            Expr::Call { name, args, kwargs } => {
                 // Pipe adds one implicit arg, so check arity with +1
-                if let Some(SymbolInfo { kind: SymbolKind::Function { param_count }, .. }) = self.resolve(name) {
-                    let pc = *param_count;
-                    if args.len() + 1 != pc {
-                        self.errors.push(LatchError::ArgCountMismatch {
-                            name: name.clone(),
-                            expected: pc,
-                            found: args.len() + 1,
-                        });
-                    }
-                }
+                self.check_call_arity(name, args, kwargs, 1);
                 for arg in args { self.check_expr(arg); }
+                for (_, v) in kwargs { self.check_expr(v); }
             }
-            Expr::ModuleCall { args, .. } => {
+            Expr::ModuleCall { module, method, args, kwargs } => {
+                let spread = args.iter().any(|a| matches!(a, Expr::Spread(_)));
+                self.check_variant(module, method, if spread { None } else { Some(args.len()) });
+                if module == "super" && self.resolve("super").is_none() {
+                    self.errors.push(LatchError::UndefinedVariable(module.clone()));
+                }
                 for arg in args { self.check_expr(arg); }
+                for (_, v) in kwargs { self.check_expr(v); }
             }
             // `expr |> func() or default` — the OrDefault wraps the call
             Expr::OrDefault { expr: inner, default } => {
@@ -547,3 +926,14 @@ impl SemanticAnalyzer {
         }
     }
 }
+
+/// If `found` is outside `min..=max`, return the bound it violated (for the error message).
+fn arity_mismatch(min: usize, max: usize, found: usize) -> Option<usize> {
+    if found < min {
+        Some(min)
+    } else if found > max {
+        Some(max)
+    } else {
+        None
+    }
+}